@@ -10,7 +10,7 @@ use rusqlite::{
 };
 use std::cell::Cell;
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, HashMap, HashSet},
     fs::{self, File},
     io::{BufRead, BufReader},
     path::{Path, PathBuf},
@@ -41,6 +41,15 @@ pub struct HLSym {
     pub seg: u8,
     pub scope: i64,
 }
+// a c function that has a body in the loaded program
+pub struct CFunction {
+    pub name: String,   // c name, ie 'main' not '_main'
+    pub module: String, // module that defines it
+    pub scope: i64,     // scope holding the args and locals
+    pub addr: u16,      // entry point
+    pub size: u16,
+    pub has_params: bool,
+}
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum SymbolType {
     Unknown,
@@ -174,6 +183,61 @@ impl DebugData {
         Ok(v)
     }
 
+    // addresses of function entry points in the code segment
+    // ie c functions and assembler .proc's (they own a scope)
+    // plus exported labels (most of the runtime is written that way)
+    pub fn get_function_entries(&self) -> Result<HashSet<u16>> {
+        let rows = self.query_db(
+            params![SegmentType::Code as u8],
+            "select symdef.val from symdef, segment
+             where symdef.seg = segment.id and segment.type = ?1
+             and (symdef.id in (select sym from scope where type = 'scope')
+                or symdef.id in (select exp from symref))",
+        )?;
+        let mut entries = HashSet::new();
+        for row in rows {
+            entries.insert(row[0].vto_i64()? as u16);
+        }
+        Ok(entries)
+    }
+
+    // the nearest function entry at or below addr (same definition of
+    // function as get_function_entries)
+    pub fn find_function_by_addr(&self, addr: u16) -> Result<Option<(String, u16)>> {
+        let rows = self.query_db(
+            params![SegmentType::Code as u8, addr],
+            "select symdef.name, symdef.val from symdef, segment
+             where symdef.seg = segment.id and segment.type = ?1 and symdef.val <= ?2
+             and (symdef.id in (select sym from scope where type = 'scope')
+                or symdef.id in (select exp from symref))
+             order by symdef.val desc limit 1",
+        )?;
+        if let Some(row) = rows.first() {
+            return Ok(Some((row[0].vto_string()?, row[1].vto_i64()? as u16)));
+        }
+        Ok(None)
+    }
+    // the closest label at or below addr in a segment, with its size if known
+    pub fn find_label_below(
+        &self,
+        addr: u16,
+        seg: u8,
+    ) -> Result<Option<(String, u16, Option<u16>)>> {
+        let rows = self.query_db(
+            params![seg, addr],
+            "select name, val, size from symdef
+             where seg = ?1 and val <= ?2 and type = 'lab'
+             order by val desc limit 1",
+        )?;
+        if let Some(row) = rows.first() {
+            let size = match row[2] {
+                SqlValue::Null => None,
+                _ => Some(row[2].vto_i64()? as u16),
+            };
+            return Ok(Some((row[0].vto_string()?, row[1].vto_i64()? as u16, size)));
+        }
+        Ok(None)
+    }
     pub fn get_symbol(&self, name: &str) -> Result<Vec<(String, u16, String)>> {
         let mut v = Vec::new();
 
@@ -638,6 +702,119 @@ impl DebugData {
             },
         }
     }
+    // finds c functions by their c name
+    pub fn find_cfunction(&self, name: &str) -> Result<Vec<CFunction>> {
+        let (module, name) = if let Some((m, n)) = name.split_once('.') {
+            (m.to_string(), n.to_string())
+        } else {
+            (String::new(), name.to_string())
+        };
+        Ok(self
+            .query_cfunctions(Some(&name))?
+            .into_iter()
+            .filter(|f| module.is_empty() || module == f.module)
+            .collect())
+    }
+    // every c function in the program
+    pub fn get_cfunctions(&self) -> Result<Vec<CFunction>> {
+        self.query_cfunctions(None)
+    }
+    // a function definition is an 'ext' csymbol whose assembler symbol
+    // owns a scope, declarations of external functions have no scope
+    fn query_cfunctions(&self, name: Option<&str>) -> Result<Vec<CFunction>> {
+        let sql = "select csymbol.name, module.name, scope.id, symdef.val, symdef.size,
+                    (select count(*) from csymbol p where p.scope = scope.id and p.sc = 'auto' and p.offset >= 0)
+                    from csymbol, scope, symdef
+                    left join module on module.id = scope.module
+                    where (?1 is null or csymbol.name = ?1) and csymbol.sc = 'ext' and scope.type = 'scope'
+                    and scope.sym = csymbol.sym and symdef.id = csymbol.sym";
+        let mut stmt = self.conn.prepare_cached(sql)?;
+        let rows = stmt.query_map(params![name], |row| {
+            let name = row.get::<usize, String>(0)?;
+            let module = row.get::<usize, Option<String>>(1)?.unwrap_or_default();
+            let scope = row.get::<usize, i64>(2)?;
+            let addr = row.get::<usize, i64>(3)? as u16;
+            let size = row.get::<usize, Option<i64>>(4)?.unwrap_or(0) as u16;
+            let params = row.get::<usize, i64>(5)?;
+            Ok(CFunction {
+                name,
+                module: module.strip_suffix(".o").unwrap_or(&module).to_string(),
+                scope,
+                addr,
+                size,
+                has_params: params > 0,
+            })
+        })?;
+        let mut v = Vec::new();
+        for row in rows {
+            v.push(row?);
+        }
+        Ok(v)
+    }
+    // the auto (stack) variables and parameters of a c function
+    pub fn get_cautos(&self, scope: i64) -> Result<Vec<HLSym>> {
+        let rows = self.query_db(
+            params![scope],
+            "select name, offset from csymbol where scope = ?1 and sc = 'auto'",
+        )?;
+        let mut v = Vec::new();
+        for row in rows {
+            v.push(HLSym {
+                name: row[0].vto_string()?,
+                type_: "auto".to_string(),
+                scope,
+                seg: 0,
+                value: row[1].vto_i64()?,
+            });
+        }
+        Ok(v)
+    }
+    // the modules that came from a library (the c runtime)
+    pub fn get_library_modules(&self) -> Result<HashSet<i32>> {
+        let rows = self.query_db(&[], "select id from module where lib not null")?;
+        let mut mods = HashSet::new();
+        for row in rows {
+            mods.insert(row[0].vto_i64()? as i32);
+        }
+        Ok(mods)
+    }
+    // code outside the code segments, the runtime keeps self modifying
+    // routines in DATA. Those are the asm lines without a data type
+    // (.byte, .word ... have one, the parser stores none as 0), ignoring
+    // any that overlap data
+    pub fn get_data_code_spans(&self) -> Result<Vec<(u16, u16)>> {
+        let rows = self.query_db(
+            params![SegmentType::Code as u8],
+            "select segment.start + s.start, s.size from span s, segment
+             where s.seg = segment.id and segment.type != ?1
+             and s.aline not null and s.type = 0
+             and not exists (select 1 from span t where t.seg = s.seg and t.type != 0
+                and t.start < s.start + s.size and s.start < t.start + t.size)",
+        )?;
+        let mut v = Vec::new();
+        for row in rows {
+            v.push((row[0].vto_i64()? as u16, row[1].vto_i64()? as u16));
+        }
+        Ok(v)
+    }
+    // the register variables of a c function, value is the offset into regbank
+    pub fn get_cregisters(&self, scope: i64) -> Result<Vec<HLSym>> {
+        let rows = self.query_db(
+            params![scope],
+            "select name, offset from csymbol where scope = ?1 and sc = 'reg'",
+        )?;
+        let mut v = Vec::new();
+        for row in rows {
+            v.push(HLSym {
+                name: row[0].vto_string()?,
+                type_: "reg".to_string(),
+                scope,
+                seg: 0,
+                value: row[1].vto_i64()?,
+            });
+        }
+        Ok(v)
+    }
     pub fn find_csym(&self, name: &str, scope: i64) -> Result<Option<HLSym>> {
         let sql = "select scope, sc,sym,offset from csymbol  where csymbol.scope =?1 and name = ?2";
         let mut stmt = self.conn.prepare_cached(sql)?;
@@ -205,7 +205,8 @@ impl DebugData {
                     if seg.addrsize == "zeropage" {
                         seg_type = SegmentType::Zp;
                     }
-                    if seg.name == "CODE" {
+                    // the segments the cc65 linker configs put code in
+                    if matches!(seg.name.as_str(), "CODE" | "STARTUP" | "ONCE" | "LOWCODE") {
                         seg_type = SegmentType::Code;
                     }
                     segcount += 1;
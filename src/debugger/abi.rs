@@ -0,0 +1,139 @@
+/*
+Checks the cc65 calling convention on every return to c code
+
+Only functions that can be called from c count (their names start with
+'_'), the runtime helpers (pushax, incsp2 ...) move sp65 on purpose
+
+- the callee pops its own arguments. When we know how many bytes of them
+  a c function has sp65 must be back to where it was before the caller
+  pushed them. Otherwise (an asm function, or c arguments of unknown size)
+  sp65 must not be below where it was at the jsr (something left pushed)
+  or above the top of the calling c function's frame (popped too much)
+- the register bank (zeropage.regbank) is preserved
+- the decimal flag is clear
+
+The last argument is passed in AX (fastcall) and pushed by the callee, so
+it does not count as pushed by the caller. The debug info has no sizes, an
+argument ends where the next one starts, so the size is only known when
+there are no pushed arguments at all. A register argument has no auto
+symbol, so a function with register variables counts as unknown too. The
+top of the caller's frame is only checked when its size is known
+*/
+use super::{
+    core::{Debugger, FrameType, JsrData},
+    cpu::{Cpu, Status},
+    execute::{AbiRule, BugType},
+};
+use anyhow::Result;
+use dbgdata::debugdb::SymbolType;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone)]
+pub(crate) struct CFuncAbi {
+    pub addr: u16,
+    pub size: u16,
+    pub stack_args: Option<u16>, // bytes of arguments the caller pushes
+}
+
+impl Debugger {
+    // the c functions of the program, sorted by address
+    pub(crate) fn load_c_functions(&self) -> Result<Vec<CFuncAbi>> {
+        let mut funcs = Vec::new();
+        for func in self.dbgdb.get_cfunctions()? {
+            // the last argument is at offset 0, anything above it was
+            // pushed by the caller
+            let pushed = self
+                .dbgdb
+                .get_cautos(func.scope)?
+                .iter()
+                .any(|a| a.value > 0);
+            let stack_args =
+                (!pushed && self.dbgdb.get_cregisters(func.scope)?.is_empty()).then_some(0);
+            funcs.push(CFuncAbi {
+                addr: func.addr,
+                size: func.size,
+                stack_args,
+            });
+        }
+        funcs.sort_by_key(|f| f.addr);
+        Ok(funcs)
+    }
+    // the entry points of everything c can call, c functions and asm
+    pub(crate) fn load_c_callable(&self) -> Result<HashSet<u16>> {
+        Ok(self
+            .dbgdb
+            .get_symbols(None)?
+            .into_iter()
+            .filter(|s| s.sym_type == SymbolType::Label && s.name.starts_with('_'))
+            .map(|s| s.value)
+            .collect())
+    }
+    // the c function containing addr
+    fn c_function_at(&self, addr: u16) -> Option<&CFuncAbi> {
+        let i = self.c_functions.partition_point(|f| f.addr <= addr);
+        self.c_functions
+            .get(i.checked_sub(1)?)
+            .filter(|f| (addr as u32) < f.addr as u32 + f.size as u32)
+    }
+
+    // called for the innermost frame just before its rts
+    pub(crate) fn check_call_abi(&self, jd: &JsrData) -> Option<BugType> {
+        let jsr_addr = jd.call_addr.wrapping_sub(3);
+        let Some(caller) = self.c_function_at(jsr_addr) else {
+            // only calls from c have to follow the rules
+            return None;
+        };
+        if !self.c_callable.contains(&jd.dest_addr) {
+            return None;
+        }
+        let sp65 = Cpu::read_word(Cpu::get_sp65_addr() as u16);
+        let out_by = |expected: u16| BugType::CallAbi(AbiRule::Sp65(sp65 as i32 - expected as i32));
+        let callee = self.c_functions.iter().find(|f| f.addr == jd.dest_addr);
+        if let Some(stack_args) = callee.and_then(|f| f.stack_args) {
+            let expected = jd.sp65.wrapping_add(stack_args);
+            if sp65 != expected {
+                return Some(out_by(expected));
+            }
+        } else {
+            if sp65 < jd.sp65 {
+                return Some(out_by(jd.sp65));
+            }
+            // the frame of the calling c function, the one below this one
+            let caller_frame = self
+                .stack_frames
+                .iter()
+                .rev()
+                .filter_map(|f| match &f.frame_type {
+                    FrameType::Jsr(jd) => Some(jd),
+                    _ => None,
+                })
+                .nth(1);
+            let caller_frame = caller_frame.filter(|cf| cf.dest_addr == caller.addr);
+            if let (Some(cf), Some(stack_args)) = (caller_frame, caller.stack_args) {
+                let top = cf.sp65.wrapping_add(stack_args);
+                if sp65 > top {
+                    return Some(out_by(top));
+                }
+            }
+        }
+        if let (Some(regbank), Some(saved)) = (self.regbank_addr, jd.regbank) {
+            let size = self.regbank_size.unwrap_or(6).min(6);
+            if let Some(i) = (0..size).find(|&i| Cpu::read_byte(regbank + i) != saved[i as usize]) {
+                return Some(BugType::CallAbi(AbiRule::Regbank(regbank + i)));
+            }
+        }
+        if Status::from_bits_truncate(Cpu::read_sr()).contains(Status::DECIMAL) {
+            return Some(BugType::CallAbi(AbiRule::Decimal));
+        }
+        None
+    }
+    // a copy of the register bank, taken at each jsr
+    pub(crate) fn save_regbank(&self) -> Option<[u8; 6]> {
+        let regbank = self.regbank_addr?;
+        let mut saved = [0; 6];
+        for (i, b) in saved.iter_mut().enumerate() {
+            *b = Cpu::read_byte(regbank + i as u16);
+        }
+        Some(saved)
+    }
+}
@@ -7,14 +7,25 @@ the same functionality as the cli shell.
 
 use anyhow::{bail, Result};
 use evalexpr::Value;
+use regex::Regex;
 
 use crate::{
+    debugger::abi::CFuncAbi,
     debugger::cpu::{Cpu, ShadowFlags},
-    debugger::execute::StopReason,
+    debugger::execute::{BugReport, StopReason, TrapMode},
+    debugger::heap::{FailPolicy, HeapEvent, HeapStats},
+    debugger::intercepts::AllocWrapper,
+    debugger::lint::{Lint, Lints},
     debugger::loader,
+    debugger::paravirt::{PARAVIRT_BASE, PV_NAMES},
+    debugger::suppress::Suppression,
+    debugger::taint::RegTaint,
+};
+use dbgdata::debugdb::{
+    CFunction, DebugData, HLSym, SegChunk, Segment, SegmentType, SourceInfo, SymbolType,
 };
-use dbgdata::debugdb::{DebugData, HLSym, SegChunk, Segment, SegmentType, SourceInfo, SymbolType};
 use std::cell::RefCell;
+use std::collections::{HashSet, VecDeque};
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
@@ -49,10 +60,11 @@ pub struct CodeLocation {
     pub parent: String,
 }
 
-type InterceptFunc = fn(&mut Debugger, bool) -> Result<Option<StopReason>>;
+pub(crate) type InterceptFunc = fn(&mut Debugger, bool) -> Result<Option<StopReason>>;
 pub struct Debugger {
     pub(crate) break_points: BTreeMap<u16, BreakPoint>,
     pub(crate) watch_points: BTreeMap<u16, WatchPoint>,
+    pub(crate) catch_points: BTreeMap<u16, CatchPoint>,
     pub(crate) source_info: BTreeMap<u16, SourceInfo>,
     pub(crate) current_file: Option<i64>,
     pub(crate) next_bp: Option<u16>,
@@ -65,12 +77,51 @@ pub struct Debugger {
     pub(crate) enable_stack_check: bool,
     pub(crate) enable_mem_check: bool,
     pub(crate) enable_heap_check: bool,
+    pub(crate) enable_seg_check: bool,
+    pub(crate) heap_redzone: u16,
+    pub(crate) heap_verify: bool,
+    pub(crate) uninit_track: bool,
+    pub(crate) null_guard: u16,        // pointers below this are NULL
+    pub(crate) smc_allow: Vec<String>, // modules that may patch their code
+    pub(crate) lints: Lints,
+    pub(crate) lint_seen: HashSet<(u16, Lint)>,
+    pub(crate) reg_taint: RegTaint,
+    pub(crate) taint_origin: Vec<u16>, // where each TAINTED byte came from
+    pub(crate) sp65_last: u16,         // c stack pointer at the last call or return
+    pub(crate) sp65_floor: u16,        // deepest the c stack has been
+    pub(crate) hw_sp_floor: u8,        // deepest the hardware stack has been
+    pub(crate) stack_size: Option<u16>, // __STACKSIZE__
+    pub(crate) stack_region: Option<(u16, u16)>, // c stack, bottom..top
+    pub(crate) heapptr_addr: Option<u16>, // top of the heap is kept here
+    pub(crate) c_functions: Vec<CFuncAbi>,
+    pub(crate) c_callable: HashSet<u16>, // entry points of '_' functions
+    pub(crate) lib_modules: HashSet<i32>, // the runtime library modules
+    pub(crate) trap_mode: TrapMode,
+    pub(crate) bug_log: Vec<BugReport>,
+    pub(crate) suppressions: Vec<Suppression>,
     pub(crate) load_name: String,
     pub(crate) run_done: bool,
     pub(crate) dbgdb: DebugData,
     pub(crate) seg_list: Vec<Segment>,
     pub(crate) heap_blocks: HashMap<u16, HeapBlock>,
-    pub(crate) privileged_mode: bool,
+    pub(crate) freed_blocks: VecDeque<HeapBlock>,
+    pub(crate) bug_pc: Option<u16>,
+    pub(crate) privileged_depth: u8, // inside the allocator
+    pub(crate) quarantine: VecDeque<HeapBlock>,
+    pub(crate) quarantine_bytes: usize,
+    pub(crate) quarantine_size: u16,
+    pub(crate) heap_events: VecDeque<HeapEvent>,
+    pub(crate) heap_seq: usize,
+    pub(crate) heap_alloc_count: usize,
+    pub(crate) heap_break: Option<usize>,
+    pub(crate) heap_fail: Option<FailPolicy>,
+    pub(crate) heap_fail_rng: u64,
+    pub(crate) heap_bytes: usize, // total asked for this run
+    pub(crate) skip_call: bool,
+    pub(crate) alloc_wrapper: Option<AllocWrapper>,
+    pub(crate) heap_regions: Vec<(u16, u16)>, // added by _heapadd
+    pub(crate) heap_stats: HeapStats,
+    pub(crate) realloc_from: Option<(u16, u16)>, // block being realloced
 
     pub(crate) regbank_addr: Option<u16>,
     pub(crate) regbank_size: Option<u16>,
@@ -80,11 +131,48 @@ pub struct Debugger {
     pub(crate) dbg_file: Option<PathBuf>,
 }
 
+#[derive(Debug, Clone)]
 pub struct HeapBlock {
     pub addr: u16,
     pub size: u16,
+    pub alloc_num: usize, // 1 for the first malloc or realloc of a run, 2 for the next ...
     pub alloc_addr: u16,
+    pub alloc_stack: Vec<u16>, // callers of alloc_addr, innermost first
+    pub redzone: u16,          // inaccessible bytes after the block
     pub realloc_size: Option<u16>,
+    pub free_addr: Option<u16>, // set once the block is freed
+    pub free_stack: Vec<u16>,
+}
+impl HeapBlock {
+    // is addr in the block or its red zone
+    pub fn covers(&self, addr: u16) -> bool {
+        addr >= self.addr && (addr as u32) < self.addr as u32 + (self.size + self.redzone) as u32
+    }
+}
+// cc65's default stack size, for when __STACKSIZE__ is not in the debug info
+pub(crate) const DEFAULT_STACKSIZE: u16 = 0x800;
+// how many freed blocks we remember for bug reports
+pub(crate) const FREED_HISTORY: usize = 32;
+
+// what we know about a data address, used to explain bugs
+#[derive(Debug, Default)]
+pub struct AddrInfo {
+    pub segment: Option<String>,
+    pub symbol: Option<(String, u16)>, // covering symbol + offset into it
+    pub cvar: Option<(String, u16)>,   // c auto variable + offset into it
+    pub creg: Option<String>,          // c register variable
+    pub heap: Option<HeapBlock>,       // live or freed (free_addr set) block
+    pub c_stack: Option<u16>,          // offset above sp65
+    pub hw_stack: bool,
+    pub dead_stack: bool, // popped off the c or hardware stack
+}
+
+// how deep the stacks got during a run
+#[derive(Debug)]
+pub struct StackUsage {
+    pub c_used: u16,
+    pub c_size: Option<u16>,
+    pub hw_used: u16,
 }
 
 #[derive(Debug)]
@@ -93,6 +181,7 @@ pub struct JsrData {
     pub call_addr: u16,
     pub sp: u8,
     pub sp65: u16,
+    pub regbank: Option<[u8; 6]>, // at the time of the call
 }
 #[derive(Debug)]
 pub struct PushData {
@@ -103,6 +192,7 @@ pub struct PushData {
 #[derive(Debug)]
 pub(crate) enum FrameType {
     Jsr(JsrData), // addr, return addr,sp,sp65
+    Brk(JsrData), // irq vector, return addr,sp,sp65
     Pha(PushData),
     Php(PushData),
 }
@@ -116,6 +206,7 @@ pub struct BreakPoint {
     pub(crate) addr: u16,
     pub(crate) symbol: String,
     pub(crate) temp: bool,
+    pub(crate) group: Option<String>, // set by rbreak
 }
 #[derive(Debug, Clone)]
 pub enum WatchType {
@@ -129,12 +220,25 @@ pub struct WatchPoint {
     pub(crate) symbol: String,
     pub(crate) watch: WatchType,
 }
+#[derive(Debug, Clone, PartialEq)]
+pub enum CatchWhen {
+    Before,
+    After,
+    Both,
+}
+#[derive(Debug, Clone)]
+pub struct CatchPoint {
+    pub(crate) addr: u16, // the PV hook address
+    pub(crate) name: String,
+    pub(crate) when: CatchWhen,
+}
 impl Debugger {
     pub fn new() -> Self {
         Cpu::reset();
         let s = Self {
             break_points: BTreeMap::new(),
             watch_points: BTreeMap::new(),
+            catch_points: BTreeMap::new(),
             source_info: BTreeMap::new(),
             current_file: None,
             loader_start: 0,
@@ -144,6 +248,28 @@ impl Debugger {
             enable_stack_check: false,
             enable_mem_check: false,
             enable_heap_check: false,
+            enable_seg_check: false,
+            heap_redzone: 8,
+            heap_verify: false,
+            uninit_track: true,
+            null_guard: 1,
+            smc_allow: Vec::new(),
+            lints: Lints::empty(),
+            lint_seen: HashSet::new(),
+            reg_taint: RegTaint::default(),
+            taint_origin: vec![0; 0x10000],
+            sp65_last: 0,
+            sp65_floor: 0,
+            hw_sp_floor: 0xff,
+            stack_size: None,
+            stack_region: None,
+            heapptr_addr: None,
+            c_functions: Vec::new(),
+            c_callable: HashSet::new(),
+            lib_modules: HashSet::new(),
+            trap_mode: TrapMode::Stop,
+            bug_log: Vec::new(),
+            suppressions: Vec::new(),
             next_bp: None,
             load_name: String::new(),
             run_done: false,
@@ -152,7 +278,24 @@ impl Debugger {
             source_mode: SourceDebugMode::None,
             call_intercepts: HashMap::new(),
             heap_blocks: HashMap::new(),
-            privileged_mode: false,
+            freed_blocks: VecDeque::new(),
+            bug_pc: None,
+            privileged_depth: 0,
+            quarantine: VecDeque::new(),
+            quarantine_bytes: 0,
+            quarantine_size: 256,
+            heap_events: VecDeque::new(),
+            heap_seq: 0,
+            heap_alloc_count: 0,
+            heap_break: None,
+            heap_fail: None,
+            heap_fail_rng: 0,
+            heap_bytes: 0,
+            skip_call: false,
+            alloc_wrapper: None,
+            heap_regions: Vec::new(),
+            heap_stats: HeapStats::default(),
+            realloc_from: None,
 
             regbank_addr: None,
             regbank_size: None,
@@ -162,10 +305,13 @@ impl Debugger {
             dbg_file: None,
         };
         let ctrlc = s.ctrlc.clone();
-        ctrlc::set_handler(move || {
+        // there is only one debugger, except in the tests
+        match ctrlc::set_handler(move || {
             ctrlc.store(true, Ordering::SeqCst);
-        })
-        .expect("Error setting Ctrl-C handler");
+        }) {
+            Ok(()) | Err(ctrlc::Error::MultipleHandlers) => {}
+            Err(e) => panic!("Error setting Ctrl-C handler: {}", e),
+        }
         s
     }
     pub fn delete_breakpoint(&mut self, id_opt: Option<&String>) -> Result<()> {
@@ -181,6 +327,17 @@ impl Debugger {
         };
         Ok(())
     }
+    // deletes all the break points created by one rbreak
+    pub fn delete_breakpoint_group(&mut self, group: &str) -> Result<usize> {
+        let before = self.break_points.len();
+        self.break_points
+            .retain(|_, bp| bp.group.as_deref() != Some(group));
+        let count = before - self.break_points.len();
+        if count == 0 {
+            bail!("No break points in group '{}'", group);
+        }
+        Ok(count)
+    }
     pub fn delete_watchpoint(&mut self, id_opt: Option<&String>) -> Result<()> {
         if let Some(id) = id_opt {
             if let Ok(num) = id.parse::<usize>() {
@@ -195,18 +352,106 @@ impl Debugger {
         Ok(())
     }
     pub fn set_break(&mut self, addr_str: &str, temp: bool) -> Result<()> {
-        let (bp_addr, save_sym) = self.convert_addr(addr_str)?;
+        // a c function name breaks after the prologue, anything else
+        // is a plain address
+        let (bp_addr, save_sym) = if let Some(addr) = self.find_cfunction_break(addr_str)? {
+            (addr, addr_str.to_string())
+        } else {
+            self.convert_addr(addr_str)?
+        };
         self.break_points.insert(
             bp_addr,
             BreakPoint {
                 addr: bp_addr,
                 symbol: save_sym,
                 temp,
+                group: None,
             },
         );
         Ok(())
     }
 
+    // sets a break point on every function whose name matches the regex
+    // the break points are grouped under the regex so they can be deleted together
+    // returns the names of the functions
+    pub fn set_regex_break(
+        &mut self,
+        pattern: &str,
+        module: Option<&String>,
+    ) -> Result<Vec<String>> {
+        let re = Regex::new(pattern)?;
+        let entries = self.dbgdb.get_function_entries()?;
+        let mut names = Vec::new();
+        for sym in self.dbgdb.get_symbols(None)? {
+            if sym.sym_type != SymbolType::Label
+                || !entries.contains(&sym.value)
+                || !re.is_match(&sym.name)
+            {
+                continue;
+            }
+            if let Some(m) = module {
+                if sym.module.strip_suffix(".o").unwrap_or(&sym.module) != m {
+                    continue;
+                }
+            }
+            // leave existing break points alone
+            if self.break_points.contains_key(&sym.value) {
+                continue;
+            }
+            self.break_points.insert(
+                sym.value,
+                BreakPoint {
+                    addr: sym.value,
+                    symbol: sym.name.clone(),
+                    temp: false,
+                    group: Some(pattern.to_string()),
+                },
+            );
+            names.push(sym.name);
+        }
+        Ok(names)
+    }
+
+    pub fn set_catch(&mut self, name: &str, when: CatchWhen) -> Result<()> {
+        let idx = PV_NAMES
+            .iter()
+            .position(|n| *n == name)
+            .ok_or_else(|| anyhow::anyhow!("Unknown system call '{}'", name))?;
+        let addr = PARAVIRT_BASE + idx as u16;
+        // there is no 'after' for exit
+        let when = if name == "exit" {
+            CatchWhen::Before
+        } else {
+            when
+        };
+        self.catch_points.insert(
+            addr,
+            CatchPoint {
+                addr,
+                name: name.to_string(),
+                when,
+            },
+        );
+        Ok(())
+    }
+    pub fn delete_catchpoint(&mut self, id_opt: Option<&String>) -> Result<()> {
+        if let Some(id) = id_opt {
+            if let Ok(num) = id.parse::<usize>() {
+                if num == 0 {
+                    bail!("catch points are numbered from 1");
+                }
+                if let Some(find) = self.catch_points.iter().map(|e| *e.0).nth(num - 1) {
+                    self.catch_points.remove(&find);
+                }
+            } else {
+                self.catch_points.retain(|_, cp| cp.name != *id);
+            }
+        } else {
+            self.catch_points.clear();
+        };
+        Ok(())
+    }
+
     pub fn set_watch(&mut self, addr_str: &str, wt: WatchType) -> Result<()> {
         let (wp_addr, save_sym) = self.convert_addr(addr_str)?;
         self.watch_points.insert(
@@ -265,16 +510,42 @@ impl Debugger {
                 }
             }
         }
+        for (start, size) in self.dbgdb.get_data_code_spans()? {
+            for i in start..start.wrapping_add(size) {
+                shadow[i as usize] |= ShadowFlags::EXECUTE;
+            }
+        }
         // hardware stack
         for i in 0x100..0x200 {
             shadow[i] = ShadowFlags::READ | ShadowFlags::WRITE;
         }
-        // sp65 stack
-        for i in (0xfff0 - 0x800)..0xfff0 {
-            shadow[i] = ShadowFlags::READ | ShadowFlags::WRITE;
+        // sp65 stack, if we dont know where it is yet it gets done
+        // once crt0 has set it up
+        if let Some((bottom, top)) = self.stack_region {
+            for i in bottom..top {
+                shadow[i as usize] = ShadowFlags::READ | ShadowFlags::WRITE;
+            }
         }
         Ok(())
     }
+    // the c stack region from the linker symbols, if they are in the debug info
+    fn find_stack_region(&self) -> Result<Option<(u16, u16)>> {
+        let sym = |name: &str| -> Result<Option<u16>> {
+            Ok(self.dbgdb.get_symbol(name)?.first().map(|s| s.1))
+        };
+        let Some(size) = self.stack_size else {
+            return Ok(None);
+        };
+        if let Some(start) = sym("__STACKSTART__")? {
+            return Ok(Some((start, start.wrapping_add(size))));
+        }
+        // the stack sits on top of the MAIN area
+        if let (Some(start), Some(main_size)) = (sym("__MAIN_START__")?, sym("__MAIN_SIZE__")?) {
+            let bottom = start.wrapping_add(main_size);
+            return Ok(Some((bottom, bottom.wrapping_add(size))));
+        }
+        Ok(None)
+    }
     pub fn load_dbg(&mut self, file: &Path) -> Result<()> {
         let fd = File::open(file)?;
         let mut reader = BufReader::new(fd);
@@ -287,6 +558,13 @@ impl Debugger {
         self.dbgdb.load_all_source_files(&mut self.source_info)?;
 
         self.load_intercepts()?;
+        self.stack_size = self.dbgdb.get_symbol("__STACKSIZE__")?.first().map(|s| s.1);
+        self.stack_region = self.find_stack_region()?;
+        self.heapptr_addr = self
+            .dbgdb
+            .get_symbol("_heap.___heapptr")?
+            .first()
+            .map(|s| s.1);
         self.init_shadow()?;
         //   self.dbgdb.load_files(&mut self.file_table)?;
 
@@ -298,20 +576,55 @@ impl Debugger {
         if !regbanksize.is_empty() {
             self.regbank_size = Some(regbanksize[0].1);
         }
+        self.c_functions = self.load_c_functions()?;
+        self.c_callable = self.load_c_callable()?;
+        self.lib_modules = self.dbgdb.get_library_modules()?;
         self.dbg_file = Some(file.to_path_buf());
         self.enable_heap_check = true;
         self.enable_mem_check = true;
         self.enable_stack_check = true;
+        self.enable_seg_check = true;
         Ok(())
     }
 
-    fn reset(&mut self) {
+    // what a run leaves behind, a new run or a new program starts afresh
+    fn reset_run_state(&mut self) {
         self.stack_frames.clear();
         self.heap_blocks.clear();
+        self.freed_blocks.clear();
+        self.quarantine.clear();
+        self.quarantine_bytes = 0;
+        self.privileged_depth = 0;
+        self.heap_events.clear();
+        self.heap_seq = 0;
+        self.heap_alloc_count = 0;
+        self.heap_bytes = 0;
+        self.alloc_wrapper = None;
+        self.heap_regions.clear();
+        self.heap_stats = HeapStats::default();
+        self.realloc_from = None;
+        self.reg_taint = RegTaint::default();
+        self.sp65_last = 0;
+        self.sp65_floor = 0;
+        self.hw_sp_floor = 0xff;
+        self.bug_log.clear();
+        self.lint_seen.clear();
+        self.suppressions.iter_mut().for_each(|s| s.hits = 0);
+        // same seed, same failures every run
+        if let Some(FailPolicy::Random { seed, .. }) = self.heap_fail {
+            self.heap_fail_rng = seed;
+        }
+    }
+    fn reset(&mut self) {
+        self.reset_run_state();
         self.run_done = false;
         self.next_bp = None;
         self.source_mode = SourceDebugMode::None;
         self.ticks = 0;
+        // a new program, nothing has been patched or run yet
+        Cpu::get_shadow()
+            .iter_mut()
+            .for_each(|f| f.remove(ShadowFlags::PATCHED | ShadowFlags::OPERAND));
         Cpu::reset();
     }
     pub fn load_code(&mut self, file: &Path) -> Result<(u16, u16)> {
@@ -384,8 +697,7 @@ impl Debugger {
         for arg in &cmd_args {
             Cpu::push_arg(arg)
         }
-        self.stack_frames.clear();
-        self.heap_blocks.clear();
+        self.reset_run_state();
 
         self.run_done = true;
         self.execute(0) // 0 = forever
@@ -450,6 +762,60 @@ impl Debugger {
         }
     }
 
+    // looks for a c function called 'name' (or 'module.name')
+    // returns the address of the first instruction after the prologue
+    pub fn find_cfunction_break(&self, name: &str) -> Result<Option<u16>> {
+        if !name
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        {
+            return Ok(None);
+        }
+        let funcs = self.dbgdb.find_cfunction(name)?;
+        match funcs.len() {
+            0 => Ok(None),
+            1 => Ok(Some(self.skip_prologue(&funcs[0])?)),
+            _ => bail!("C function '{}' is ambiguous", name),
+        }
+    }
+
+    // cc65 puts the prologue in the span of the first c line
+    // so we cannot just use the line table like gdb does.
+    // Instead walk the code skipping
+    //  - the push of a fastcall argument (only if there are args)
+    //  - stack reservation for locals (decspn, ldy #n / jsr subysp)
+    //  - variadic entry (ldy #n / jsr enter)
+    fn skip_prologue(&self, func: &CFunction) -> Result<u16> {
+        const PUSH: [&str; 4] = ["pusha", "pusha0", "pushax", "pusheax"];
+        const RESERVE: [&str; 2] = ["subysp", "enter"];
+
+        let end = func.addr.saturating_add(func.size.max(3));
+        let mut addr = func.addr;
+        let mut pushed = !func.has_params;
+        while addr < end {
+            let (ldy, jsr) = match Cpu::read_byte(addr) {
+                0xa0 if Cpu::read_byte(addr + 2) == 0x20 => (2, addr + 2),
+                0x20 => (0, addr),
+                _ => break,
+            };
+            let target = self.symbol_lookup(Cpu::read_word(jsr + 1))?;
+            let skip = match ldy {
+                0 if !pushed && PUSH.contains(&target.as_str()) => {
+                    pushed = true;
+                    true
+                }
+                0 => target.starts_with("decsp"),
+                _ => RESERVE.contains(&target.as_str()),
+            };
+            if !skip {
+                break;
+            }
+            addr = jsr + 3;
+        }
+        Ok(addr)
+    }
+
     // reverse of convert_addr.
     // tried to find a symbol matching an address
     // if not found it returns a numeric string
@@ -482,6 +848,18 @@ impl Debugger {
         Ok(format!("${:02x}", addr))
     }
 
+    // the module code or data at addr came from
+    pub(crate) fn module_chunk(&self, addr: u16) -> Option<&SegChunk> {
+        self.seg_list
+            .iter()
+            .flat_map(|s| {
+                s.modules
+                    .iter()
+                    .map(move |m| (s.start.wrapping_add(m.offset), m))
+            })
+            .find(|(start, m)| addr >= *start && (addr as u32) < *start as u32 + m.size as u32)
+            .map(|(_, m)| m)
+    }
     pub fn _find_module(&self, addr: u16) -> Option<&SegChunk> {
         if self.seg_list.is_empty() {
             return None;
@@ -511,6 +889,21 @@ impl Debugger {
         self.dbgdb.find_csym(name, scope)
     }
 
+    // one line description of a code address, source file:line if we have it
+    pub fn location_string(&self, addr: u16) -> Result<String> {
+        let waw = self.where_are_we(addr)?;
+        let line = match (waw.cfile, waw.afile) {
+            (Some(cf), _) => Some((cf, waw.cline)),
+            (_, Some(af)) => Some((af, waw.aline)),
+            _ => None,
+        };
+        if let Some((file_id, line_no)) = line {
+            if let Some(file) = self.lookup_file_by_id(file_id) {
+                return Ok(format!("0x{:04x} {}:{}", addr, file.short_name, line_no));
+            }
+        }
+        Ok(format!("0x{:04x} {}", addr, waw.parent))
+    }
     pub fn where_are_we(&self, addr: u16) -> Result<CodeLocation> {
         // given an address find out where we are
         // finds seg, module, assembly line and c line
@@ -638,20 +1031,7 @@ impl Debugger {
             if let Some(csym) = self.dbgdb.find_csym(name, scope)? {
                 match csym.type_.as_str() {
                     "auto" => {
-                        //let stack = self.debugger.read_stack();
-                        let mut sp65 = 0;
-                        for i in (0..self.stack_frames.len()).rev() {
-                            if let FrameType::Jsr(jsr) = &self.stack_frames[i].frame_type {
-                                sp65 = jsr.sp65;
-                                if i == 0 {
-                                    // the call main stack frame is out by 4 (argc,argv)
-                                    sp65 -= 4;
-                                }
-                                break;
-                            }
-                        }
-
-                        return Ok(Some((sp65 as i64 + csym.value) as u16));
+                        return Ok(Some((self.frame_sp65() as i64 + csym.value) as u16));
                     }
                     "reg" => {
                         if let Some(regbank) = self.regbank_addr {
@@ -667,4 +1047,130 @@ impl Debugger {
         }
         Ok(None)
     }
+    // the sp65 value that the current function's autos are relative to
+    fn frame_sp65(&self) -> u16 {
+        let mut sp65 = 0;
+        for i in (0..self.stack_frames.len()).rev() {
+            if let FrameType::Jsr(jsr) = &self.stack_frames[i].frame_type {
+                sp65 = jsr.sp65;
+                if i == 0 {
+                    // the call main stack frame is out by 4 (argc,argv)
+                    sp65 -= 4;
+                }
+                break;
+            }
+        }
+        sp65
+    }
+
+    // find out all we can about a data address
+    pub fn describe_address(&self, addr: u16) -> Result<AddrInfo> {
+        let mut info = AddrInfo::default();
+        if let Some(seg) = self.seg_list.iter().find(|s| {
+            s.size > 0 && s.start <= addr && (s.start as u32 + s.size as u32) > addr as u32
+        }) {
+            info.segment = Some(seg.name.clone());
+            if let Some((name, val, size)) = self.dbgdb.find_label_below(addr, seg.id)? {
+                if size.is_none_or(|sz| addr - val < sz) {
+                    info.symbol = Some((name, addr - val));
+                }
+            }
+        }
+
+        // heap - live blocks first, then the recently freed ones
+        info.heap = self
+            .heap_blocks
+            .values()
+            .find(|hb| hb.addr != 0 && hb.covers(addr))
+            .or_else(|| self.quarantine.iter().rev().find(|hb| hb.covers(addr)))
+            .or_else(|| self.freed_blocks.iter().rev().find(|hb| hb.covers(addr)))
+            .cloned();
+
+        // the c stack runs from sp65 up to where it was when the
+        // outermost call was made
+        let sp65 = Cpu::read_word(Cpu::get_sp65_addr() as u16);
+        let top = self.stack_frames.iter().find_map(|f| match &f.frame_type {
+            FrameType::Jsr(jd) => Some(jd.sp65),
+            _ => None,
+        });
+        if let Some(top) = top {
+            if addr >= sp65 && addr < top {
+                info.c_stack = Some(addr - sp65);
+                info.cvar = self.find_cauto(addr)?;
+            }
+        }
+        info.hw_stack = (0x100..0x200).contains(&addr);
+        info.dead_stack = self.in_dead_stack(addr);
+        info.creg = self.find_creg(addr)?;
+        Ok(info)
+    }
+
+    // the register variable of the current function kept at addr
+    fn find_creg(&self, addr: u16) -> Result<Option<String>> {
+        let Some(regbank) = self.regbank_addr else {
+            return Ok(None);
+        };
+        if addr < regbank || addr >= regbank + self.regbank_size.unwrap_or(6) {
+            return Ok(None);
+        }
+        let waw = self.where_are_we(self.read_pc())?;
+        let Some(scope) = waw.scope else {
+            return Ok(None);
+        };
+        Ok(self
+            .dbgdb
+            .get_cregisters(scope)?
+            .into_iter()
+            .find(|r| r.value == (addr - regbank) as i64)
+            .map(|r| r.name))
+    }
+
+    // which auto variable of the current function covers addr
+    // we dont know the variable sizes, so each one is assumed to run up to
+    // the next one, the last parameter is assumed to be a 16 bit value
+    fn find_cauto(&self, addr: u16) -> Result<Option<(String, u16)>> {
+        let waw = self.where_are_we(self.read_pc())?;
+        let Some(scope) = waw.scope else {
+            return Ok(None);
+        };
+        let base = self.frame_sp65() as i64;
+        let mut autos = self
+            .dbgdb
+            .get_cautos(scope)?
+            .into_iter()
+            .map(|a| (base + a.value, a.name))
+            .collect::<Vec<_>>();
+        autos.sort();
+        let target = addr as i64;
+        for (i, (start, name)) in autos.iter().enumerate() {
+            let end = match autos.get(i + 1) {
+                Some((next, _)) => *next,
+                None if *start < base => base,
+                None => start + 2,
+            };
+            if target >= *start && target < end {
+                return Ok(Some((name.clone(), (target - start) as u16)));
+            }
+        }
+        Ok(None)
+    }
+}
+#[test]
+fn catch_exit() {
+    use crate::debugger::paravirt::{PvArgs, PvCall};
+    let _cpu = Cpu::test_lock();
+    let mut dbg = Debugger::new();
+    dbg.set_catch("exit", CatchWhen::Before).unwrap();
+    // lda #3, jsr to the exit hook
+    Cpu::test_code(0x0200, &[0xa9, 0x03, 0x20, 0xf9, 0xff]);
+    assert!(matches!(
+        dbg.execute(10).unwrap(),
+        StopReason::Catch(PvCall {
+            args: PvArgs::Exit { code: 3 },
+            result: None
+        })
+    ));
+    assert!(dbg.delete_catchpoint(Some(&"0".to_string())).is_err());
+    dbg.delete_catchpoint(Some(&"1".to_string())).unwrap();
+    assert!(matches!(dbg.execute(10).unwrap(), StopReason::Exit(3)));
 }
@@ -64,6 +64,8 @@ bitflags! {
         const WRITE =       0b0000_0100;   // writable
         const TAINTED =     0b0000_1000; // contents derived from uniti data
         const READ =        0b0001_0000;  // free memory
+        const PATCHED =     0b0010_0000; // code changed by the program
+        const OPERAND =     0b0100_0000; // executed as part of an instruction
 
     }
 }
@@ -101,6 +103,9 @@ extern "C" fn MemWriteByte(addr: u32, val: u8) {
         } else {
             THECPU.memcheck = MemCheck::WriteNoPermission(addr as u16);
         }
+        if flags.contains(ShadowFlags::EXECUTE) {
+            THECPU.shadow[addr as usize] |= ShadowFlags::PATCHED;
+        }
         THECPU.shadow[addr as usize] |= ShadowFlags::WRITTEN;
         THECPU.memhits[THECPU.memhitcount as usize] = (true, addr as u16);
         THECPU.memhitcount += 1;
@@ -198,6 +203,10 @@ impl Cpu {
             CPU = cpu;
         }
     }
+    // 0 = 6502, 1 = 65c02
+    pub fn get_cpu() -> u16 {
+        unsafe { CPU }
+    }
     pub fn sp65_addr(v: u8) {
         unsafe {
             THECPU.sp65_addr = v;
@@ -324,14 +333,18 @@ impl Cpu {
     pub fn write_byte(addr: u16, val: u8) {
         unsafe {
             THECPU.inner_write_byte(addr, val);
-            THECPU.shadow[addr as usize] |= ShadowFlags::WRITTEN;
+            // whatever the debugger writes is a defined value
+            THECPU.shadow[addr as usize].insert(ShadowFlags::WRITTEN);
+            THECPU.shadow[addr as usize].remove(ShadowFlags::TAINTED);
         }
     }
     pub fn write_word(addr: u16, val: u16) {
         unsafe {
             THECPU.inner_write_word(addr, val);
-            THECPU.shadow[addr as usize] |= ShadowFlags::WRITTEN;
-            THECPU.shadow[(addr as usize) + 1] |= ShadowFlags::WRITTEN;
+            for i in 0..2 {
+                THECPU.shadow[addr as usize + i].insert(ShadowFlags::WRITTEN);
+                THECPU.shadow[addr as usize + i].remove(ShadowFlags::TAINTED);
+            }
         }
     }
 
@@ -414,8 +427,29 @@ impl fmt::Debug for Status {
     }
 }
 
+// the tests share the one cpu, the ones that use it take turns
+#[cfg(test)]
+static TEST_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+#[cfg(test)]
+impl Cpu {
+    pub fn test_lock() -> std::sync::MutexGuard<'static, ()> {
+        // there is no shell to print through
+        let _ = util::say::SAY_CB.set(|s, _| println!("{}", s));
+        // one failed test must not fail all the others
+        TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    // put some code at addr and point pc at it
+    pub fn test_code(addr: u16, code: &[u8]) {
+        for (i, b) in code.iter().enumerate() {
+            Cpu::write_byte(addr + i as u16, *b);
+        }
+        Cpu::write_pc(addr);
+    }
+}
+
 #[test]
 fn regreadwrite() {
+    let _cpu = Cpu::test_lock();
     Cpu::reset();
     Cpu::write_ac(1);
     Cpu::write_xr(2);
@@ -10,10 +10,14 @@ Its responsible for running the code . It detects
 It runs until it stops. It then returns a StopReason
 */
 use anyhow::Result;
+use std::{fmt, mem::discriminant};
+use util::say;
 #[derive(Debug, Clone)]
 pub enum StopReason {
     BreakPoint(u16),
     WatchPoint(u16),
+    Catch(PvCall),
+    HeapBreak(usize), // allocation number
     Exit(u8),
     Count,
     Next,
@@ -28,15 +32,297 @@ pub enum BugType {
     Memcheck(u16),
     HeapCheck,
     SegCheck(u16),
+    HeapOverflow(u16),
+    UseAfterFree(u16),
+    UninitValue(UninitUse, u16), // where the undefined value came from
+    DeadStack(u16),
+    StackOverflow(u16),        // sp65
+    ReturnCorrupted(u16, u16), // where it should return to, where it will
+    CallAbi(AbiRule),
+    NullDeref(u16),     // the pointer value
+    ExecViolation(u16), // where control went
+    CodeWrite(u16),
+}
+// which part of the cc65 calling convention a function broke
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AbiRule {
+    Sp65(i32), // how far out it is
+    Regbank(u16),
+    Decimal,
+}
+// what an undefined value was used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UninitUse {
+    Branch,
+    Address,
+    SysCall,
+}
+impl BugType {
+    // the names used in suppression files
+    pub const NAMES: [&'static str; 14] = [
+        "SpMismatch",
+        "Memcheck",
+        "HeapCheck",
+        "SegCheck",
+        "HeapOverflow",
+        "UseAfterFree",
+        "UninitValue",
+        "DeadStack",
+        "StackOverflow",
+        "ReturnCorrupted",
+        "CallAbi",
+        "NullDeref",
+        "ExecViolation",
+        "CodeWrite",
+    ];
+    pub fn name(&self) -> &'static str {
+        match self {
+            BugType::SpMismatch => Self::NAMES[0],
+            BugType::Memcheck(_) => Self::NAMES[1],
+            BugType::HeapCheck => Self::NAMES[2],
+            BugType::SegCheck(_) => Self::NAMES[3],
+            BugType::HeapOverflow(_) => Self::NAMES[4],
+            BugType::UseAfterFree(_) => Self::NAMES[5],
+            BugType::UninitValue(..) => Self::NAMES[6],
+            BugType::DeadStack(_) => Self::NAMES[7],
+            BugType::StackOverflow(_) => Self::NAMES[8],
+            BugType::ReturnCorrupted(..) => Self::NAMES[9],
+            BugType::CallAbi(_) => Self::NAMES[10],
+            BugType::NullDeref(_) => Self::NAMES[11],
+            BugType::ExecViolation(_) => Self::NAMES[12],
+            BugType::CodeWrite(_) => Self::NAMES[13],
+        }
+    }
+}
+impl fmt::Display for BugType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BugType::SpMismatch => write!(f, "Stack pointer mismatch"),
+            BugType::Memcheck(addr) => write!(f, "Unitialized memory read -> ${:04x}", addr),
+            BugType::HeapCheck => write!(f, "Heap check failed"),
+            BugType::SegCheck(addr) => write!(f, "Seg read/write violation -> ${:04x}", addr),
+            BugType::HeapOverflow(addr) => write!(f, "Heap block overflow -> ${:04x}", addr),
+            BugType::UseAfterFree(addr) => write!(f, "Use of freed memory -> ${:04x}", addr),
+            BugType::UninitValue(UninitUse::Branch, from) => {
+                write!(
+                    f,
+                    "Branch depends on uninitialized value from ${:04x}",
+                    from
+                )
+            }
+            BugType::UninitValue(UninitUse::Address, from) => {
+                write!(f, "Address uses uninitialized value from ${:04x}", from)
+            }
+            BugType::UninitValue(UninitUse::SysCall, from) => {
+                write!(f, "System call uses uninitialized value from ${:04x}", from)
+            }
+            BugType::DeadStack(addr) => write!(f, "Read of popped stack memory -> ${:04x}", addr),
+            BugType::StackOverflow(sp65) => write!(f, "C stack overflow, sp65 -> ${:04x}", sp65),
+            BugType::ReturnCorrupted(expected, actual) => write!(
+                f,
+                "Return address corrupted, returning to ${:04x} instead of ${:04x}",
+                actual, expected
+            ),
+            BugType::CallAbi(AbiRule::Sp65(out)) => {
+                write!(
+                    f,
+                    "Calling convention: sp65 is out by {} bytes on return",
+                    out
+                )
+            }
+            BugType::CallAbi(AbiRule::Regbank(addr)) => write!(
+                f,
+                "Calling convention: register bank not restored -> ${:04x}",
+                addr
+            ),
+            BugType::CallAbi(AbiRule::Decimal) => {
+                write!(f, "Calling convention: decimal flag set on return")
+            }
+            BugType::NullDeref(ptr) => write!(f, "NULL pointer dereference -> ${:04x}", ptr),
+            BugType::ExecViolation(addr) => {
+                write!(f, "Execution outside the code segments -> ${:04x}", addr)
+            }
+            BugType::CodeWrite(addr) => write!(f, "Write to code -> ${:04x}", addr),
+        }
+    }
+}
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrapMode {
+    Stop, // a bug stops execution
+    Warn, // a bug is logged and execution continues
+}
+// a bug seen in warn mode. Repeats of the same bug type
+// at the same place with the same call stack just bump the count
+#[derive(Debug, Clone)]
+pub struct BugReport {
+    pub bug: BugType,
+    pub pc: u16,
+    pub stack: Vec<u16>, // return addresses, innermost first
+    pub count: usize,
 }
 use crate::{
-    debugger::core::{Debugger, FrameType, SourceDebugMode, StackFrame, WatchType},
-    debugger::cpu::{Cpu, MemCheck},
+    debugger::core::{
+        CatchWhen, Debugger, FrameType, SourceDebugMode, StackFrame, WatchType, DEFAULT_STACKSIZE,
+    },
+    debugger::cpu::{Cpu, MemCheck, ShadowFlags},
+    debugger::paravirt::{ParaVirt, PvCall, PARAVIRT_BASE, PV_NAMES},
+    debugger::taint::indirect_pointer,
 };
 use anyhow::anyhow;
 
 use super::core::{JsrData, PushData};
 impl Debugger {
+    /*
+        Follows both stacks after each instruction
+        - memory popped off either stack is dead, it no longer holds a
+          defined value and reading it is a bug (typically a pointer to an
+          auto variable of a function that has returned)
+        - records how deep they got
+        - the c stack must stay inside its region and out of the heap
+    */
+    fn track_stacks(&mut self, inst: u8, old_sp: u8) -> Option<BugType> {
+        let shadow = Cpu::get_shadow();
+        let sp = Cpu::read_sp();
+        if inst == 0x9a {
+            // txs, a new stack (crt0 setting it up)
+            self.hw_sp_floor = sp;
+        }
+        self.hw_sp_floor = self.hw_sp_floor.min(sp);
+        if sp > old_sp {
+            for i in (old_sp as usize + 1)..=sp as usize {
+                shadow[0x100 + i].remove(ShadowFlags::WRITTEN | ShadowFlags::TAINTED);
+            }
+        }
+        // the c stack pointer is updated a byte at a time, so only look
+        // at it on calls and returns, where it is consistent
+        if inst != 0x20 && inst != 0x60 {
+            return None;
+        }
+        let sp65 = Cpu::read_word(Cpu::get_sp65_addr() as u16);
+        if sp65 == 0 {
+            // crt0 has not set up the c stack yet
+            return None;
+        }
+        if self.sp65_last == 0 && self.stack_region.is_none() {
+            // no linker symbols, crt0 has just set sp to the top of the stack
+            let size = self.stack_size.unwrap_or(DEFAULT_STACKSIZE);
+            let bottom = sp65.saturating_sub(size);
+            for addr in bottom..sp65 {
+                shadow[addr as usize] |= ShadowFlags::READ | ShadowFlags::WRITE;
+            }
+            self.stack_region = Some((bottom, sp65));
+        }
+        if self.sp65_last != 0 && sp65 > self.sp65_last {
+            for addr in self.sp65_last..sp65 {
+                shadow[addr as usize].remove(ShadowFlags::WRITTEN | ShadowFlags::TAINTED);
+            }
+        }
+        if self.sp65_floor == 0 || sp65 < self.sp65_floor {
+            self.sp65_floor = sp65;
+        }
+        self.sp65_last = sp65;
+
+        // the heap grows up towards the stack
+        let heap_top = self.heapptr_addr.map_or(0, Cpu::read_word);
+        match self.stack_region {
+            Some((bottom, _)) if sp65 < bottom || sp65 < heap_top => {
+                Some(BugType::StackOverflow(sp65))
+            }
+            _ => None,
+        }
+    }
+    // is addr free stack space, below the c or the hardware stack pointer
+    pub(crate) fn in_dead_stack(&self, addr: u16) -> bool {
+        let sp65 = Cpu::read_word(Cpu::get_sp65_addr() as u16);
+        (self.sp65_floor != 0 && addr >= self.sp65_floor && addr < sp65)
+            || (0x100..=0x100 + Cpu::read_sp() as u16).contains(&addr)
+    }
+    // compare the return address about to be pulled off the hardware stack
+    // (above sp) with the one pushed when the frame was made. rts adds 1
+    fn check_return(sp: u8, expected: u16, adjust: u16) -> Option<BugType> {
+        let lo = Cpu::read_byte(0x100 | sp.wrapping_add(1) as u16) as u16;
+        let hi = Cpu::read_byte(0x100 | sp.wrapping_add(2) as u16) as u16;
+        let actual = (lo | hi << 8).wrapping_add(adjust);
+        (actual != expected).then_some(BugType::ReturnCorrupted(expected, actual))
+    }
+    // has the instruction at pc just sent control somewhere that is not
+    // code? Only once per excursion, from code to not code. Needs the
+    // segments from the debug info, the PV hooks are code too
+    fn check_exec(&self, pc: u16) -> Option<BugType> {
+        if !self.enable_seg_check || self.seg_list.is_empty() {
+            return None;
+        }
+        let shadow = Cpu::get_shadow();
+        let next = Cpu::read_pc();
+        let is_code = |addr: u16| {
+            shadow[addr as usize].contains(ShadowFlags::EXECUTE)
+                || (PARAVIRT_BASE..PARAVIRT_BASE + PV_NAMES.len() as u16).contains(&addr)
+        };
+        (is_code(pc) && !is_code(next)).then_some(BugType::ExecViolation(next))
+    }
+    // is the code at addr in a module that is allowed to patch itself
+    fn smc_allowed(&self, addr: u16) -> bool {
+        if self.smc_allow.is_empty() {
+            return false;
+        }
+        self.module_chunk(addr).is_some_and(|m| {
+            let name = m.module_name.trim_end_matches(".o");
+            self.smc_allow
+                .iter()
+                .any(|a| a == "*" || a.trim_end_matches(".o") == name)
+        })
+    }
+    // return addresses of the active calls, innermost first
+    pub(crate) fn call_stack(&self) -> Vec<u16> {
+        self.stack_frames
+            .iter()
+            .rev()
+            .filter_map(|f| match &f.frame_type {
+                FrameType::Jsr(jd) => Some(jd.call_addr),
+                _ => None,
+            })
+            .collect()
+    }
+    // the callers of the code at pc. Intercepts fire at a jsr
+    // whose frame has already been pushed, that frame is pc itself
+    pub(crate) fn bug_stack(&self, pc: u16) -> Vec<u16> {
+        let mut stack = self.call_stack();
+        if Cpu::read_byte(pc) == 0x20 && stack.first() == Some(&pc.wrapping_add(3)) {
+            stack.remove(0);
+        }
+        stack
+    }
+    // a check has fired at pc. In stop mode that is the end of the run,
+    // in warn mode we log it and carry on
+    pub(crate) fn trap(&mut self, pc: u16, bug: BugType) -> Result<Option<StopReason>> {
+        if self.is_suppressed(pc, &bug)? {
+            return Ok(None);
+        }
+        if self.trap_mode == TrapMode::Stop {
+            self.bug_pc = Some(pc);
+            return Ok(Some(StopReason::Bug(bug)));
+        }
+        let stack = self.bug_stack(pc);
+        if let Some(rep) = self
+            .bug_log
+            .iter_mut()
+            .find(|r| r.pc == pc && discriminant(&r.bug) == discriminant(&bug) && r.stack == stack)
+        {
+            rep.count += 1;
+            return Ok(None);
+        }
+        say!("Warning: {} at {}", bug, self.location_string(pc)?);
+        for addr in &stack {
+            say!("    called from {}", self.location_string(*addr)?);
+        }
+        self.bug_log.push(BugReport {
+            bug,
+            pc,
+            stack,
+            count: 1,
+        });
+        Ok(None)
+    }
     pub fn execute(&mut self, mut count: u16) -> Result<StopReason> {
         let counting = count > 0;
 
@@ -57,6 +343,37 @@ impl Debugger {
 
             let inst = Cpu::read_byte(pc);
             let mut deferred_stop: Option<StopReason> = None;
+
+            // about to make a PV call that we want to see the result of?
+            // the args are gone once the call is done so grab them now
+            let pv_call = if self.catch_points.is_empty() {
+                None
+            } else {
+                ParaVirt::pv_target(pc)
+                    .filter(|t| {
+                        self.catch_points
+                            .get(t)
+                            .is_some_and(|cp| cp.when != CatchWhen::Before)
+                    })
+                    .map(ParaVirt::peek_args)
+            };
+
+            // going through a NULL pointer? Reported after the instruction
+            // has run (reading or writing $0000 does no harm in here)
+            let null_deref = match indirect_pointer(pc) {
+                Some((_, ptr)) if ptr < self.null_guard => Some(BugType::NullDeref(ptr)),
+                _ => None,
+            };
+
+            self.lint_before(pc)?;
+
+            // follow undefined values, any use of one is reported
+            // after the instruction has run, with the other memory bugs
+            let uninit_used = if self.enable_mem_check && self.uninit_track {
+                self.track_taint(pc)
+            } else {
+                None
+            };
             match inst {
                 0x20 => {
                     // jsr
@@ -73,6 +390,7 @@ impl Debugger {
                             call_addr: pc + 3,
                             sp,
                             sp65,
+                            regbank: self.save_regbank(),
                         }),
                         stop_on_pop: false,
                     });
@@ -87,8 +405,26 @@ impl Debugger {
 
                 0x60 => {
                     // rts
+                    // the stack is balanced but is the return address still
+                    // the one the jsr pushed? Checked before the pop so
+                    // the report has the call stack of the damaged frame
+                    if let Some(StackFrame {
+                        frame_type: FrameType::Jsr(jd),
+                        ..
+                    }) = self.stack_frames.last()
+                    {
+                        let sp = Cpu::read_sp();
+                        if self.enable_stack_check && sp.wrapping_add(2) == jd.sp {
+                            // stop once the rts has gone wherever it goes
+                            if let Some(bug) = Self::check_return(sp, jd.call_addr, 1) {
+                                deferred_stop = self.trap(pc, bug)?;
+                            } else if let Some(bug) = self.check_call_abi(jd) {
+                                deferred_stop = self.trap(pc, bug)?;
+                            }
+                        }
+                    }
                     if let Some(frame) = self.stack_frames.pop() {
-                        if frame.stop_on_pop {
+                        if frame.stop_on_pop && deferred_stop.is_none() {
                             // defer til after we execute the rts
                             deferred_stop = Some(StopReason::Finish);
                         }
@@ -97,7 +433,9 @@ impl Debugger {
                             if self.enable_stack_check {
                                 let sp = Cpu::read_sp();
                                 if sp + 2 != jd.sp {
-                                    break StopReason::Bug(BugType::SpMismatch);
+                                    if let Some(stop) = self.trap(pc, BugType::SpMismatch)? {
+                                        break stop;
+                                    }
                                 }
                             }
                             if let Some(intercept) = self.call_intercepts.get(&jd.dest_addr) {
@@ -111,7 +449,9 @@ impl Debugger {
                             //break StopReason::Bug(BugType::SpMismatch);
                         }
                     } else if self.enable_stack_check {
-                        break StopReason::Bug(BugType::SpMismatch);
+                        if let Some(stop) = self.trap(pc, BugType::SpMismatch)? {
+                            break stop;
+                        }
                     }
                 }
                 0x68 => {
@@ -126,7 +466,9 @@ impl Debugger {
                             }
                         }
                     } else if self.enable_stack_check {
-                        break StopReason::Bug(BugType::SpMismatch);
+                        if let Some(stop) = self.trap(pc, BugType::SpMismatch)? {
+                            break stop;
+                        }
                     }
                 }
                 0x48 => {
@@ -147,10 +489,16 @@ impl Debugger {
                     if let Some(fr) = self.stack_frames.pop() {
                         // ok - but it should be a push frame
                         if let FrameType::Jsr(_jd) = fr.frame_type {
-                            break StopReason::Bug(BugType::SpMismatch);
+                            if self.enable_stack_check {
+                                if let Some(stop) = self.trap(pc, BugType::SpMismatch)? {
+                                    break stop;
+                                }
+                            }
                         }
                     } else if self.enable_stack_check {
-                        break StopReason::Bug(BugType::SpMismatch);
+                        if let Some(stop) = self.trap(pc, BugType::SpMismatch)? {
+                            break stop;
+                        }
                     }
                 }
                 0x08 => {
@@ -165,52 +513,148 @@ impl Debugger {
                         stop_on_pop: false,
                     });
                 }
+                0x00 => {
+                    // brk, pushes the address after its signature byte
+                    // and the flags
+                    let sp65_addr = Cpu::get_sp65_addr();
+                    self.stack_frames.push(StackFrame {
+                        frame_type: FrameType::Brk(JsrData {
+                            dest_addr: Cpu::read_word(0xfffe),
+                            call_addr: pc.wrapping_add(2),
+                            sp: Cpu::read_sp(),
+                            sp65: Cpu::read_word(sp65_addr as u16),
+                            regbank: None,
+                        }),
+                        stop_on_pop: false,
+                    });
+                }
                 0x40 => {
                     // rti
-                    let _ = self.stack_frames.pop();
+                    if let Some(StackFrame {
+                        frame_type: FrameType::Brk(bd),
+                        ..
+                    }) = self.stack_frames.pop()
+                    {
+                        // skip the flags
+                        let sp = Cpu::read_sp().wrapping_add(1);
+                        if self.enable_stack_check && sp.wrapping_add(2) == bd.sp {
+                            if let Some(bug) = Self::check_return(sp, bd.call_addr, 0) {
+                                deferred_stop = self.trap(pc, bug)?;
+                            }
+                        }
+                    }
                 }
                 _ => {}
             };
 
-            // Now execute the instruction
-            self.ticks += Cpu::execute_insn() as usize;
+            let old_sp = Cpu::read_sp();
+            // Now execute the instruction, unless an intercept has
+            // already done the call for us (an injected malloc failure)
+            if self.skip_call {
+                self.skip_call = false;
+                self.stack_frames.pop();
+                Cpu::write_pc(pc + 3);
+            } else {
+                self.ticks += Cpu::execute_insn() as usize;
+            }
 
             // PVExit called?
             if let Some(exit_code) = Cpu::exit_done() {
                 self.run_done = false;
                 break StopReason::Exit(exit_code);
             }
+            self.lint_after(pc, inst)?;
 
             if Cpu::was_paracall() {
                 // a PV call pops the stack but we do not see an rts
                 // so we have a dangling stack frame - pop it
                 self.stack_frames.pop().ok_or(anyhow!("stack underflow"))?;
+                if let Some(args) = pv_call {
+                    break 'main_loop StopReason::Catch(PvCall {
+                        args,
+                        result: Some(ParaVirt::get_ax()),
+                    });
+                }
+            }
+
+            if let Some(bug) = self.track_stacks(inst, old_sp) {
+                if self.enable_stack_check {
+                    if let Some(stop) = self.trap(pc, bug)? {
+                        break 'main_loop stop;
+                    }
+                }
             }
 
             // invalid memory read check
-            if self.enable_mem_check && !self.privileged_mode {
-                match Cpu::get_memcheck() {
-                    MemCheck::None => {}
-                    MemCheck::ReadNoWrite(addr) => {
+            if self.privileged_depth == 0 {
+                let bug = match Cpu::get_memcheck() {
+                    MemCheck::None => None,
+                    MemCheck::ReadNoWrite(addr) | MemCheck::WriteNoPermission(addr)
+                        if self.enable_heap_check && self.in_redzone(*addr) =>
+                    {
+                        Some(BugType::HeapOverflow(*addr))
+                    }
+                    MemCheck::ReadNoWrite(addr) | MemCheck::WriteNoPermission(addr)
+                        if self.enable_heap_check && self.in_quarantine(*addr) =>
+                    {
+                        Some(BugType::UseAfterFree(*addr))
+                    }
+                    MemCheck::ReadNoWrite(addr)
+                        if self.enable_mem_check && self.in_dead_stack(*addr) =>
+                    {
+                        Some(BugType::DeadStack(*addr))
+                    }
+                    // with uninit tracking on only a read outside readable
+                    // memory is a bug here, the rest is taint
+                    MemCheck::ReadNoWrite(addr)
+                        if self.enable_mem_check
+                            && (!self.uninit_track
+                                || !Cpu::get_shadow()[*addr as usize]
+                                    .contains(ShadowFlags::READ)) =>
+                    {
                         // register save area is regulary read before write
                         if let Some(regbank) = self.regbank_addr {
                             if *addr >= regbank
                                 && *addr < (regbank + self.regbank_size.unwrap_or(6))
                             {
                                 // OK
+                                None
                             } else {
-                                break 'main_loop StopReason::Bug(BugType::Memcheck(*addr));
+                                Some(BugType::Memcheck(*addr))
                             }
                         } else {
-                            break 'main_loop StopReason::Bug(BugType::Memcheck(*addr));
+                            Some(BugType::Memcheck(*addr))
                         }
                     }
-                    MemCheck::WriteNoPermission(addr) => {
-                        break 'main_loop StopReason::Bug(BugType::SegCheck(*addr));
+                    MemCheck::WriteNoPermission(addr)
+                        if self.enable_seg_check
+                            && Cpu::get_shadow()[*addr as usize].contains(ShadowFlags::EXECUTE) =>
+                    {
+                        (!self.smc_allowed(*addr)).then_some(BugType::CodeWrite(*addr))
+                    }
+                    MemCheck::WriteNoPermission(addr) if self.enable_seg_check => {
+                        Some(BugType::SegCheck(*addr))
+                    }
+                    _ => None,
+                }
+                .or(null_deref)
+                .or(uninit_used);
+                if let Some(bug) = bug {
+                    if let Some(stop) = self.trap(pc, bug)? {
+                        break 'main_loop stop;
                     }
                 }
             }
 
+            // did that jump (or fall) out of the code? Reported against
+            // the instruction that did it, a jump through a bad function
+            // pointer is no use to debug from where it lands
+            if let Some(bug) = self.check_exec(pc) {
+                if let Some(stop) = self.trap(pc, bug)? {
+                    break 'main_loop stop;
+                }
+            }
+
             // limited number of instructions?
             if counting {
                 count -= 1;
@@ -265,6 +709,20 @@ impl Debugger {
                 break StopReason::BreakPoint(pc);
             }
 
+            // is the next instruction a PV call we want to stop before?
+            if !self.catch_points.is_empty() {
+                if let Some(target) = ParaVirt::pv_target(pc) {
+                    if let Some(cp) = self.catch_points.get(&target) {
+                        if cp.when != CatchWhen::After {
+                            break 'main_loop StopReason::Catch(PvCall {
+                                args: ParaVirt::peek_args(target),
+                                result: None,
+                            });
+                        }
+                    }
+                }
+            }
+
             // source mode next and step
             match &self.source_mode {
                 SourceDebugMode::None => {}
@@ -295,3 +753,84 @@ impl Debugger {
         Ok(reason)
     }
 }
+#[test]
+fn wild_read() {
+    let _cpu = Cpu::test_lock();
+    let mut dbg = Debugger::new();
+    dbg.enable_mem_check = true;
+    // nothing mapped at $dead, undefined or not it is not taint
+    Cpu::get_shadow()[0xdead] = ShadowFlags::empty();
+    // lda $dead
+    Cpu::test_code(0x0200, &[0xad, 0xad, 0xde]);
+    assert!(matches!(
+        dbg.execute(1).unwrap(),
+        StopReason::Bug(BugType::Memcheck(0xdead))
+    ));
+}
+#[test]
+fn dead_stack() {
+    let _cpu = Cpu::test_lock();
+    let mut dbg = Debugger::new();
+    dbg.enable_mem_check = true;
+    let sp = Cpu::get_sp65_addr();
+    Cpu::write_word(sp as u16, 0x7010);
+    Cpu::get_shadow()[0x7000] = ShadowFlags::READ | ShadowFlags::WRITE;
+    // push a local, call something that pops it, then read it
+    Cpu::test_code(
+        0x0200,
+        &[
+            0xa9, 0x00, 0x85, sp, // lda #0, sta sp
+            0xa9, 0xaa, 0x8d, 0x00, 0x70, // lda #$aa, sta $7000
+            0x20, 0x00, 0x03, // jsr $0300
+            0xad, 0x00, 0x70, // lda $7000
+        ],
+    );
+    // lda #$10, sta sp, rts
+    Cpu::test_code(0x0300, &[0xa9, 0x10, 0x85, sp, 0x60]);
+    Cpu::write_pc(0x0200);
+    assert!(matches!(
+        dbg.execute(20).unwrap(),
+        StopReason::Bug(BugType::DeadStack(0x7000))
+    ));
+}
+#[test]
+fn stack_overflow() {
+    let _cpu = Cpu::test_lock();
+    let mut dbg = Debugger::new();
+    dbg.enable_stack_check = true;
+    dbg.stack_region = Some((0x7000, 0x7100));
+    Cpu::write_word(Cpu::get_sp65_addr() as u16, 0x6ff0);
+    Cpu::write_sp(0xff);
+    // jsr $0300
+    Cpu::test_code(0x0200, &[0x20, 0x00, 0x03]);
+    assert!(matches!(
+        dbg.execute(1).unwrap(),
+        StopReason::Bug(BugType::StackOverflow(0x6ff0))
+    ));
+    let usage = dbg.get_stack_usage();
+    assert_eq!(
+        (usage.c_used, usage.c_size, usage.hw_used),
+        (0x110, Some(0x100), 2)
+    );
+}
+#[test]
+fn return_corrupted() {
+    let _cpu = Cpu::test_lock();
+    let mut dbg = Debugger::new();
+    dbg.enable_stack_check = true;
+    Cpu::write_sp(0xff);
+    // jsr $0300
+    Cpu::test_code(0x0200, &[0x20, 0x00, 0x03]);
+    // tsx, inc $0101,x, rts
+    Cpu::test_code(0x0300, &[0xba, 0xfe, 0x01, 0x01, 0x60]);
+    Cpu::write_pc(0x0200);
+    let stop = dbg.execute(10).unwrap();
+    assert!(
+        matches!(
+            stop,
+            StopReason::Bug(BugType::ReturnCorrupted(0x0203, 0x0204))
+        ),
+        "{:?}",
+        stop
+    );
+}
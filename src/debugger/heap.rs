@@ -0,0 +1,733 @@
+/*
+Heap reporting built on the blocks tracked by the malloc/free intercepts
+
+Leaks are classified like valgrind memcheck does, with a conservative scan
+for pointers. Any 16 bit value (at any alignment) that points into a block
+counts as a pointer to it. The roots are the writable segments (DATA, BSS,
+ZEROPAGE, ...) and the live c stack. Not the runtime's zero page scratch
+(ptr1, tmp1, sreg ...), a library call leaves its pointers in there. Not
+AX either, at exit it is the exit code.
+
+- still reachable   : a chain of pointers from a root leads to it
+- indirectly lost   : only pointed to by lost blocks
+- definitely lost   : nothing points to it
+
+The heap checker walks cc65's own heap structures (see _heap.h in the cc65
+runtime) and cross checks them against the blocks we track
+
+  __heaporg, __heapptr, __heapend   bottom, current top and limit of the heap
+  __heapfirst, __heaplast           the free list, sorted by address
+
+  usedblock { size, start }          4 bytes in front of every user pointer
+  freeblock { size, next, prev }     at the start of every free block
+
+Sizes include the header. The used and free blocks should exactly tile the
+heap from __heaporg to __heapptr
+*/
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+};
+
+use anyhow::{bail, Result};
+
+use dbgdata::debugdb::SegmentType;
+
+use super::{
+    core::{Debugger, FrameType},
+    cpu::Cpu,
+    execute::{BugType, StopReason},
+};
+use util::{say, trace};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LeakKind {
+    Definite,
+    Indirect,
+    Reachable,
+}
+// leaked blocks that were allocated from the same place
+// with the same call stack
+pub struct LeakGroup {
+    pub kind: LeakKind,
+    pub alloc_addr: u16,
+    pub alloc_stack: Vec<u16>,
+    pub blocks: Vec<u16>,
+    pub alloc_nums: Vec<usize>,
+    pub bytes: usize,
+}
+
+// a block found walking the heap
+pub struct HeapChunk {
+    pub start: u16,
+    pub size: u16,
+    pub user: Option<u16>, // user pointer of an allocated block, None if free
+}
+pub struct HeapWalk {
+    pub org: u16,
+    pub ptr: u16,
+    pub end: u16,
+    pub chunks: Vec<HeapChunk>, // sorted by address
+    pub added: Vec<(u16, u16)>, // extra memory given to _heapadd, start and size
+    pub errors: Vec<String>,
+}
+// one malloc, free or realloc made by the program
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeapOp {
+    Malloc,
+    Free,
+    Realloc { from: u16, old_size: u16 },
+}
+#[derive(Debug, Clone)]
+pub struct HeapEvent {
+    pub seq: usize,
+    pub alloc_num: usize, // of the block allocated or freed
+    pub op: HeapOp,
+    pub addr: u16,
+    pub size: u16,
+    pub pc: u16,
+    pub ticks: usize,
+}
+impl HeapEvent {
+    // did this event allocate or free addr
+    pub fn touches(&self, addr: u16) -> bool {
+        let inside = |start: u16, size: u16| {
+            addr >= start && (addr as u32) < start as u32 + size.max(1) as u32
+        };
+        match self.op {
+            HeapOp::Realloc { from, old_size } => {
+                inside(self.addr, self.size) || inside(from, old_size)
+            }
+            _ => inside(self.addr, self.size),
+        }
+    }
+}
+#[derive(Debug, Clone, Default)]
+pub struct HeapStats {
+    pub mallocs: usize,
+    pub frees: usize,
+    pub reallocs: usize,
+    pub failed: usize,
+    pub injected: usize, // failures forced by 'heap fail'
+    pub peak_bytes: usize,
+    pub peak_blocks: usize,
+    pub peak_top: u16, // highest __heapptr seen, cc65 never lowers it much
+}
+// when 'heap fail' makes malloc and realloc return NULL
+#[derive(Debug, Clone, Copy)]
+pub enum FailPolicy {
+    Nth(usize),        // allocation number n
+    AfterBytes(usize), // any allocation that takes the total asked for past this
+    Random { probability: f64, seed: u64 },
+}
+impl fmt::Display for FailPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FailPolicy::Nth(n) => write!(f, "fail allocation #{}", n),
+            FailPolicy::AfterBytes(b) => write!(f, "fail after {} bytes", b),
+            FailPolicy::Random { probability, seed } => {
+                write!(f, "fail with probability {} (seed {})", probability, seed)
+            }
+        }
+    }
+}
+pub const HEAP_HISTORY: usize = 10000;
+const HEAP_ADMIN_SPACE: u16 = 4;
+pub(crate) const MIN_FREE_BLOCK: u16 = 6;
+// the runtime's zero page scratch, whatever a library call left in
+// there is not a live pointer. sp and regbank are not scratch
+const ZP_SCRATCH: [(&str, u16); 10] = [
+    ("sreg", 2),
+    ("regsave", 4),
+    ("ptr1", 2),
+    ("ptr2", 2),
+    ("ptr3", 2),
+    ("ptr4", 2),
+    ("tmp1", 1),
+    ("tmp2", 1),
+    ("tmp3", 1),
+    ("tmp4", 1),
+];
+
+impl Debugger {
+    // every block still allocated, grouped by leak kind and allocation stack
+    // worst first
+    pub fn leak_report(&self) -> Result<Vec<LeakGroup>> {
+        let kinds = self.classify_blocks()?;
+        let mut groups: Vec<LeakGroup> = Vec::new();
+        let mut blocks = self
+            .heap_blocks
+            .values()
+            .filter(|hb| hb.addr != 0)
+            .collect::<Vec<_>>();
+        blocks.sort_by_key(|hb| hb.addr);
+        for hb in blocks {
+            let kind = kinds[&hb.addr];
+            if let Some(group) = groups.iter_mut().find(|g| {
+                g.kind == kind && g.alloc_addr == hb.alloc_addr && g.alloc_stack == hb.alloc_stack
+            }) {
+                group.blocks.push(hb.addr);
+                group.alloc_nums.push(hb.alloc_num);
+                group.bytes += hb.size as usize;
+            } else {
+                groups.push(LeakGroup {
+                    kind,
+                    alloc_addr: hb.alloc_addr,
+                    alloc_stack: hb.alloc_stack.clone(),
+                    blocks: vec![hb.addr],
+                    alloc_nums: vec![hb.alloc_num],
+                    bytes: hb.size as usize,
+                });
+            }
+        }
+        groups.sort_by_key(|g| (g.kind, std::cmp::Reverse(g.bytes)));
+        Ok(groups)
+    }
+
+    fn classify_blocks(&self) -> Result<HashMap<u16, LeakKind>> {
+        let mut blocks = self
+            .heap_blocks
+            .values()
+            .filter(|hb| hb.addr != 0)
+            .map(|hb| (hb.addr, hb.size))
+            .collect::<Vec<_>>();
+        blocks.sort();
+        let mut scratch = Vec::new();
+        for (name, size) in ZP_SCRATCH {
+            if let Some((_, addr, _)) = self
+                .dbgdb
+                .get_symbol(&format!("zeropage.{}", name))?
+                .first()
+            {
+                scratch.extend(*addr..*addr + size);
+            }
+        }
+
+        // which blocks does this range of memory point into
+        let scan = |start: u16, len: u16| -> Vec<usize> {
+            let mut hits = Vec::new();
+            let end = (start as u32 + len as u32).min(0x10000);
+            for addr in start as u32..end.saturating_sub(1) {
+                if scratch.contains(&(addr as u16)) || scratch.contains(&(addr as u16 + 1)) {
+                    continue;
+                }
+                let ptr = Cpu::read_word(addr as u16);
+                if let Some(idx) = Self::block_index(&blocks, ptr) {
+                    hits.push(idx);
+                }
+            }
+            hits
+        };
+
+        // roots
+        let mut found = Vec::new();
+        for seg in &self.seg_list {
+            if seg.seg_type == SegmentType::ReadWrite as u8 || seg.seg_type == SegmentType::Zp as u8
+            {
+                found.extend(scan(seg.start, seg.size));
+            }
+        }
+        let sp65 = Cpu::read_word(Cpu::get_sp65_addr() as u16);
+        let top = self.stack_frames.iter().find_map(|f| match &f.frame_type {
+            FrameType::Jsr(jd) => Some(jd.sp65),
+            _ => None,
+        });
+        if let Some(top) = top {
+            if top > sp65 {
+                found.extend(scan(sp65, top - sp65));
+            }
+        }
+
+        // everything reachable from the roots
+        let mut reached = vec![false; blocks.len()];
+        let mut work = VecDeque::from(found);
+        while let Some(idx) = work.pop_front() {
+            if !reached[idx] {
+                reached[idx] = true;
+                work.extend(scan(blocks[idx].0, blocks[idx].1));
+            }
+        }
+
+        // lost blocks pointed to by other lost blocks are indirectly lost
+        // as long as they hang off a definitely lost one (pure cycles have
+        // no such start so they are definitely lost)
+        let lost = (0..blocks.len())
+            .filter(|i| !reached[*i])
+            .collect::<Vec<_>>();
+        let mut pointed_to = vec![false; blocks.len()];
+        for &idx in &lost {
+            for hit in scan(blocks[idx].0, blocks[idx].1) {
+                if hit != idx && !reached[hit] {
+                    pointed_to[hit] = true;
+                }
+            }
+        }
+        let mut indirect = vec![false; blocks.len()];
+        let mut work = lost
+            .iter()
+            .filter(|i| !pointed_to[**i])
+            .copied()
+            .collect::<VecDeque<_>>();
+        while let Some(idx) = work.pop_front() {
+            for hit in scan(blocks[idx].0, blocks[idx].1) {
+                if !reached[hit] && pointed_to[hit] && !indirect[hit] {
+                    indirect[hit] = true;
+                    work.push_back(hit);
+                }
+            }
+        }
+
+        Ok(blocks
+            .iter()
+            .enumerate()
+            .map(|(i, (addr, _))| {
+                let kind = if reached[i] {
+                    LeakKind::Reachable
+                } else if indirect[i] {
+                    LeakKind::Indirect
+                } else {
+                    LeakKind::Definite
+                };
+                (*addr, kind)
+            })
+            .collect())
+    }
+    // the c name __heapxxx is ___heapxxx in the _heap module
+    fn heap_var(&self, name: &str) -> Result<u16> {
+        let sym = self.dbgdb.get_symbol(&format!("_heap._{}", name))?;
+        if sym.is_empty() {
+            bail!("no heap in this program ({} not found)", name);
+        }
+        Ok(Cpu::read_word(sym[0].1))
+    }
+
+    // walk and validate the cc65 heap
+    pub fn check_heap(&self) -> Result<HeapWalk> {
+        let mut walk = HeapWalk {
+            org: self.heap_var("__heaporg")?,
+            ptr: self.heap_var("__heapptr")?,
+            end: self.heap_var("__heapend")?,
+            chunks: Vec::new(),
+            added: self.heap_regions.clone(),
+            errors: Vec::new(),
+        };
+        if walk.org > walk.ptr || walk.ptr > walk.end {
+            walk.errors.push(format!(
+                "heap bounds out of order org=${:04x} ptr=${:04x} end=${:04x}",
+                walk.org, walk.ptr, walk.end
+            ));
+            return Ok(walk);
+        }
+        let mut regions = vec![(walk.org as u32, walk.ptr as u32)];
+        regions.extend(
+            walk.added
+                .iter()
+                .map(|(start, size)| (*start as u32, *start as u32 + *size as u32)),
+        );
+        let in_heap = |start: u16, size: u16| {
+            let (start, end) = (start as u32, start as u32 + size as u32);
+            regions.iter().any(|(lo, hi)| start >= *lo && end <= *hi)
+        };
+
+        // the free list
+        let last = self.heap_var("__heaplast")?;
+        let mut prev = 0;
+        let mut free = self.heap_var("__heapfirst")?;
+        while free != 0 {
+            // a free block header is size, next and prev, all words
+            if free.checked_add(5).is_none() {
+                walk.errors
+                    .push(format!("free block ${:04x} has a corrupt header", free));
+                break;
+            }
+            let size = Cpu::read_word(free);
+            let next = Cpu::read_word(free + 2);
+            let back = Cpu::read_word(free + 4);
+            if !in_heap(free, size) || size < MIN_FREE_BLOCK {
+                walk.errors.push(format!(
+                    "free block ${:04x} size {} is not inside the heap",
+                    free, size
+                ));
+                break;
+            }
+            if back != prev {
+                walk.errors.push(format!(
+                    "free block ${:04x} prev link is ${:04x}, expected ${:04x}",
+                    free, back, prev
+                ));
+            }
+            if next != 0 && next <= free {
+                // also stops us going round in circles
+                walk.errors.push(format!(
+                    "free list out of order, ${:04x} is followed by ${:04x}",
+                    free, next
+                ));
+                break;
+            }
+            if free.checked_add(size) == Some(next) {
+                walk.errors.push(format!(
+                    "free blocks ${:04x} and ${:04x} are adjacent but not merged",
+                    free, next
+                ));
+            }
+            walk.chunks.push(HeapChunk {
+                start: free,
+                size,
+                user: None,
+            });
+            prev = free;
+            free = next;
+        }
+        if prev != last {
+            walk.errors.push(format!(
+                "last free block is ${:04x} but __heaplast is ${:04x}",
+                prev, last
+            ));
+        }
+
+        // the blocks we know are allocated (quarantined ones are still
+        // allocated as far as cc65 is concerned)
+        let live = self
+            .heap_blocks
+            .values()
+            .filter(|hb| hb.addr != 0)
+            .chain(self.quarantine.iter());
+        for hb in live {
+            let size = Cpu::read_word(hb.addr.wrapping_sub(HEAP_ADMIN_SPACE));
+            let start = Cpu::read_word(hb.addr.wrapping_sub(HEAP_ADMIN_SPACE - 2));
+            if !in_heap(start, size) || start > hb.addr.wrapping_sub(HEAP_ADMIN_SPACE) {
+                walk.errors.push(format!(
+                    "block ${:04x} has a bad header, start=${:04x} size={}",
+                    hb.addr, start, size
+                ));
+                continue;
+            }
+            if hb.addr as u32 + (hb.size + hb.redzone) as u32 > start as u32 + size as u32 {
+                walk.errors.push(format!(
+                    "block ${:04x} size {} does not fit in its header size {}",
+                    hb.addr, hb.size, size
+                ));
+            }
+            walk.chunks.push(HeapChunk {
+                start,
+                size,
+                user: Some(hb.addr),
+            });
+        }
+
+        // used and free blocks should cover the heap (and any memory
+        // added to it) with no gaps or overlaps
+        walk.chunks.sort_by_key(|c| c.start);
+        for (lo, hi) in regions {
+            let mut expect = lo;
+            for chunk in walk
+                .chunks
+                .iter()
+                .filter(|c| (lo..hi).contains(&(c.start as u32)))
+            {
+                let start = chunk.start as u32;
+                if start > expect {
+                    walk.errors.push(format!(
+                        "gap in the heap ${:04x}-${:04x}",
+                        expect,
+                        start - 1
+                    ));
+                } else if start < expect {
+                    walk.errors.push(format!(
+                        "block at ${:04x} overlaps the one before it",
+                        start
+                    ));
+                }
+                expect = expect.max(start + chunk.size as u32);
+            }
+            if expect != hi && lo == walk.org as u32 {
+                walk.errors.push(format!(
+                    "blocks end at ${:04x} but __heapptr is ${:04x}",
+                    expect, walk.ptr
+                ));
+            } else if expect != hi {
+                walk.errors.push(format!(
+                    "blocks in memory added at ${:04x} end at ${:04x}, not ${:04x}",
+                    lo, expect, hi
+                ));
+            }
+        }
+        Ok(walk)
+    }
+    // the biggest block malloc could hand out, with the quarantined
+    // blocks counted as free or not. Free blocks next to each other
+    // are merged, as free would have done
+    pub(crate) fn largest_free(&self, walk: &HeapWalk, with_quarantine: bool) -> u16 {
+        let is_free = |c: &HeapChunk| match c.user {
+            None => true,
+            Some(user) => with_quarantine && self.quarantine.iter().any(|hb| hb.addr == user),
+        };
+        let top = walk.end.saturating_sub(walk.ptr) as u32;
+        let mut largest = top;
+        let mut run: Option<(u32, u32)> = None;
+        for chunk in walk.chunks.iter() {
+            if !is_free(chunk) {
+                run = None;
+                continue;
+            }
+            let (start, end) = (chunk.start as u32, chunk.start as u32 + chunk.size as u32);
+            run = match run {
+                Some((run_start, run_end)) if run_end == start => Some((run_start, end)),
+                _ => Some((start, end)),
+            };
+            let (run_start, run_end) = run.unwrap();
+            // a free run at the top of the heap joins the unused space
+            let len = if run_end == walk.ptr as u32 {
+                run_end - run_start + top
+            } else {
+                run_end - run_start
+            };
+            largest = largest.max(len);
+        }
+        largest.min(0xffff) as u16
+    }
+
+    pub(crate) fn record_heap_event(
+        &mut self,
+        op: HeapOp,
+        alloc_num: usize,
+        addr: u16,
+        size: u16,
+        pc: u16,
+    ) {
+        match op {
+            HeapOp::Malloc => self.heap_stats.mallocs += 1,
+            HeapOp::Free => self.heap_stats.frees += 1,
+            HeapOp::Realloc { .. } => self.heap_stats.reallocs += 1,
+        }
+        if self.heap_events.len() == HEAP_HISTORY {
+            self.heap_events.pop_front();
+        }
+        self.heap_seq += 1;
+        self.heap_events.push_back(HeapEvent {
+            seq: self.heap_seq,
+            alloc_num,
+            op,
+            addr,
+            size,
+            pc,
+            ticks: self.ticks,
+        });
+        self.note_heap_usage();
+    }
+    // should the allocation about to be made fail?
+    pub(crate) fn should_fail(&mut self, bytes: u16) -> bool {
+        let fail = match self.heap_fail {
+            None => false,
+            Some(FailPolicy::Nth(n)) => n == self.heap_alloc_count,
+            Some(FailPolicy::AfterBytes(max)) => self.heap_bytes + bytes as usize > max,
+            Some(FailPolicy::Random { probability, .. }) => self.next_random() < probability,
+        };
+        if !fail {
+            self.heap_bytes += bytes as usize;
+            return false;
+        }
+        trace!("injected allocation failure #{}", self.heap_alloc_count);
+        self.heap_stats.failed += 1;
+        self.heap_stats.injected += 1;
+        true
+    }
+    // the intercepted call must not run, it returns NULL instead
+    pub(crate) fn skip_with_null(&mut self) {
+        Cpu::write_ac(0);
+        Cpu::write_xr(0);
+        self.skip_call = true;
+    }
+    pub(crate) fn inject_failure(&mut self, bytes: u16) -> bool {
+        if !self.should_fail(bytes) {
+            return false;
+        }
+        self.skip_with_null();
+        true
+    }
+    // splitmix64, 0..1
+    fn next_random(&mut self) -> f64 {
+        self.heap_fail_rng = self.heap_fail_rng.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.heap_fail_rng;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+    // keep the high water marks up to date
+    fn note_heap_usage(&mut self) {
+        let live = self.heap_blocks.values().filter(|hb| hb.addr != 0);
+        let blocks = live.clone().count();
+        let bytes = live.map(|hb| hb.size as usize).sum::<usize>();
+        let top = self.heap_var("__heapptr").unwrap_or(0);
+        let stats = &mut self.heap_stats;
+        stats.peak_bytes = stats.peak_bytes.max(bytes);
+        stats.peak_blocks = stats.peak_blocks.max(blocks);
+        stats.peak_top = stats.peak_top.max(top);
+    }
+    // run after every heap call when heap_verify is on
+    pub(crate) fn verify_heap(
+        &mut self,
+        ret: bool,
+        stop: Option<StopReason>,
+    ) -> Result<Option<StopReason>> {
+        if !ret || stop.is_some() || !self.heap_verify || !self.enable_heap_check {
+            return Ok(stop);
+        }
+        let walk = self.check_heap()?;
+        if walk.errors.is_empty() {
+            return Ok(None);
+        }
+        for err in &walk.errors {
+            say!("heap: {}", err);
+        }
+        self.trap(Cpu::read_pc(), BugType::HeapCheck)
+    }
+    // is addr in the red zone after a live block
+    pub(crate) fn in_redzone(&self, addr: u16) -> bool {
+        self.heap_blocks
+            .values()
+            .any(|hb| hb.addr != 0 && hb.covers(addr) && addr - hb.addr >= hb.size)
+    }
+    // is addr in a freed block that is still in quarantine
+    pub(crate) fn in_quarantine(&self, addr: u16) -> bool {
+        self.quarantine.iter().any(|hb| hb.covers(addr))
+    }
+    fn block_index(blocks: &[(u16, u16)], ptr: u16) -> Option<usize> {
+        blocks
+            .iter()
+            .position(|(addr, size)| ptr >= *addr && (ptr as u32) < *addr as u32 + *size as u32)
+    }
+}
+#[test]
+fn leak_kinds() {
+    use super::core::HeapBlock;
+    use dbgdata::debugdb::Segment;
+    let _cpu = Cpu::test_lock();
+    let mut dbg = Debugger::new();
+    dbg.seg_list.push(Segment {
+        id: 0,
+        name: "DATA".to_string(),
+        start: 0x7000,
+        size: 0x20,
+        seg_type: SegmentType::ReadWrite as u8,
+        modules: Vec::new(),
+    });
+    for addr in 0x7000..0x7700 {
+        Cpu::write_byte(addr, 0);
+    }
+    for (i, addr) in [0x7100, 0x7200, 0x7300, 0x7400, 0x7500, 0x7600]
+        .into_iter()
+        .enumerate()
+    {
+        let hb = HeapBlock {
+            addr,
+            size: 8,
+            alloc_num: i + 1,
+            alloc_addr: 0,
+            alloc_stack: Vec::new(),
+            redzone: 0,
+            realloc_size: None,
+            free_addr: None,
+            free_stack: Vec::new(),
+        };
+        dbg.heap_blocks.insert(addr, hb);
+    }
+    // a root pointer to the start of a block, one into the middle of
+    // another and a block reachable through a reachable one
+    Cpu::write_word(0x7000, 0x7100);
+    Cpu::write_word(0x7011, 0x7204);
+    Cpu::write_word(0x7102, 0x7600);
+    // a lost block, and the one only it points to
+    Cpu::write_word(0x7303, 0x7400);
+
+    let kinds = dbg.classify_blocks().unwrap();
+    assert_eq!(kinds[&0x7100], LeakKind::Reachable);
+    assert_eq!(kinds[&0x7200], LeakKind::Reachable);
+    assert_eq!(kinds[&0x7600], LeakKind::Reachable);
+    assert_eq!(kinds[&0x7300], LeakKind::Definite);
+    assert_eq!(kinds[&0x7400], LeakKind::Indirect);
+    assert_eq!(kinds[&0x7500], LeakKind::Definite);
+}
+#[test]
+fn leak_sample() {
+    use super::execute::TrapMode;
+    let _cpu = Cpu::test_lock();
+    let mut dbg = Debugger::new();
+    dbg.load_code(std::path::Path::new("samples/heap")).unwrap();
+    dbg.trap_mode = TrapMode::Warn;
+    assert!(matches!(dbg.run(vec![]).unwrap(), StopReason::Exit(_)));
+    // the realloc'd block is still in ptr2 after strcpy, that is not a root
+    let leaks = dbg.leak_report().unwrap();
+    assert_eq!(leaks.len(), 1);
+    assert_eq!((leaks[0].kind, leaks[0].bytes), (LeakKind::Definite, 20));
+}
+#[test]
+fn fail_policies() {
+    let _cpu = Cpu::test_lock();
+    let mut dbg = Debugger::new();
+    // a run of allocations, counted like the intercepts do
+    let failures = |dbg: &mut Debugger, calls: usize| {
+        dbg.heap_alloc_count = 0;
+        dbg.heap_bytes = 0;
+        (0..calls)
+            .map(|_| {
+                dbg.heap_alloc_count += 1;
+                dbg.should_fail(16)
+            })
+            .collect::<Vec<_>>()
+    };
+    assert!(!failures(&mut dbg, 10).contains(&true));
+    dbg.heap_fail = Some(FailPolicy::Nth(4));
+    let fails = failures(&mut dbg, 10);
+    assert_eq!(fails.iter().position(|f| *f), Some(3));
+    assert_eq!(fails.iter().filter(|f| **f).count(), 1);
+    assert_eq!(dbg.heap_stats.injected, 1);
+    dbg.heap_fail = Some(FailPolicy::AfterBytes(40));
+    assert_eq!(failures(&mut dbg, 4), [false, false, true, true]);
+
+    // the same seed gives the same failures
+    dbg.heap_fail = Some(FailPolicy::Random {
+        probability: 0.5,
+        seed: 42,
+    });
+    dbg.heap_fail_rng = 42;
+    let first = failures(&mut dbg, 64);
+    dbg.heap_fail_rng = 42;
+    assert_eq!(failures(&mut dbg, 64), first);
+    let count = first.iter().filter(|f| **f).count();
+    assert!(count > 16 && count < 48);
+    dbg.heap_fail_rng = 43;
+    assert_ne!(failures(&mut dbg, 64), first);
+}
+#[test]
+fn heap_walk() {
+    let _cpu = Cpu::test_lock();
+    let mut dbg = Debugger::new();
+    dbg.load_code(std::path::Path::new("samples/heap")).unwrap();
+    let set = |name: &str, val: u16| {
+        let sym = dbg.dbgdb.get_symbol(&format!("_heap._{}", name)).unwrap();
+        Cpu::write_word(sym[0].1, val);
+    };
+    // one free block covering the whole heap
+    set("__heaporg", 0x7000);
+    set("__heapptr", 0x7100);
+    set("__heapend", 0x7800);
+    set("__heapfirst", 0x7000);
+    set("__heaplast", 0x7000);
+    Cpu::write_word(0x7000, 0x100);
+    Cpu::write_word(0x7002, 0);
+    Cpu::write_word(0x7004, 0);
+    let walk = dbg.check_heap().unwrap();
+    assert!(walk.errors.is_empty(), "{:?}", walk.errors);
+    assert_eq!(walk.chunks.len(), 1);
+
+    Cpu::write_word(0x7004, 0x7080);
+    let walk = dbg.check_heap().unwrap();
+    assert!(walk.errors[0].contains("prev link"));
+
+    // a header running off the end of memory is corrupt, not a panic
+    set("__heapfirst", 0xfffe);
+    let walk = dbg.check_heap().unwrap();
+    assert!(walk.errors[0].contains("corrupt header"));
+}
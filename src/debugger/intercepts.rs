@@ -1,12 +1,78 @@
 use super::{
-    core::HeapBlock,
+    core::{HeapBlock, InterceptFunc, FREED_HISTORY},
     cpu::ShadowFlags,
-    execute::{BugType, StopReason},
+    execute::{BugType, StopReason, UninitUse},
+    heap::{HeapOp, MIN_FREE_BLOCK},
 };
 use crate::{debugger::core::Debugger, debugger::cpu::Cpu};
 use anyhow::{bail, Result};
 use util::trace;
 
+// a heap function that calls malloc or free itself, the malloc and free
+// intercepts need to know who they are working for
+#[derive(Debug)]
+pub(crate) enum AllocWrapper {
+    Calloc {
+        alloc_addr: u16,
+        alloc_stack: Vec<u16>,
+    },
+    Memalign {
+        memptr: u16,
+        size: u16,
+        redzone: u16,
+        fail: bool,
+        alloc_num: usize,
+        alloc_addr: u16,
+        alloc_stack: Vec<u16>,
+    },
+}
+
+// runtime functions whose arguments are checked before they run
+#[derive(Debug, Clone, Copy)]
+enum CheckedCall {
+    Memcpy,
+    Memmove,
+    Memset,
+    Strcpy,
+    Strcat,
+    Strlen,
+    Strncpy,
+    Fgets,
+}
+// what a checked call does with a byte
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Access {
+    Read,
+    Write,
+    Copy, // read but only to store it somewhere else
+}
+const CHECKED_CALLS: [(&str, InterceptFunc); 8] = [
+    ("memcpy._memcpy", |d, f| {
+        d.bounds_intercept(f, CheckedCall::Memcpy)
+    }),
+    ("memmove._memmove", |d, f| {
+        d.bounds_intercept(f, CheckedCall::Memmove)
+    }),
+    ("memset._memset", |d, f| {
+        d.bounds_intercept(f, CheckedCall::Memset)
+    }),
+    ("strcpy._strcpy", |d, f| {
+        d.bounds_intercept(f, CheckedCall::Strcpy)
+    }),
+    ("strcat._strcat", |d, f| {
+        d.bounds_intercept(f, CheckedCall::Strcat)
+    }),
+    ("strlen._strlen", |d, f| {
+        d.bounds_intercept(f, CheckedCall::Strlen)
+    }),
+    ("strncpy._strncpy", |d, f| {
+        d.bounds_intercept(f, CheckedCall::Strncpy)
+    }),
+    ("fgets._fgets", |d, f| {
+        d.bounds_intercept(f, CheckedCall::Fgets)
+    }),
+];
+
 impl Debugger {
     /*
 
@@ -18,8 +84,10 @@ impl Debugger {
     pub fn load_intercepts(&mut self) -> Result<()> {
         let malloc = self.dbgdb.get_symbol("malloc._malloc")?;
         if malloc.len() == 1 {
-            self.call_intercepts
-                .insert(malloc[0].1, |d, f| d.malloc_intercept(f));
+            self.call_intercepts.insert(malloc[0].1, |d, f| {
+                let stop = d.malloc_intercept(f)?;
+                d.verify_heap(f, stop)
+            });
             // only try to hook free if we managed to hook malloc
             // they really are a pair
             // TODO hook realloc because there is a case where realloc is called
@@ -27,15 +95,57 @@ impl Debugger {
             // wrapper over free/malloc
             let free = self.dbgdb.get_symbol("free._free")?;
             if free.len() == 1 {
-                self.call_intercepts
-                    .insert(free[0].1, |d, f| d.free_intercept(f));
+                self.call_intercepts.insert(free[0].1, |d, f| {
+                    let stop = d.free_intercept(f)?;
+                    d.verify_heap(f, stop)
+                });
             };
         };
         let realloc = self.dbgdb.get_symbol("realloc._realloc")?;
         if realloc.len() == 1 {
-            self.call_intercepts
-                .insert(realloc[0].1, |d, f| d.realloc_intercept(f));
+            self.call_intercepts.insert(realloc[0].1, |d, f| {
+                let stop = d.realloc_intercept(f)?;
+                d.verify_heap(f, stop)
+            });
         };
+        // these all work through malloc and free
+        if malloc.len() == 1 {
+            let calloc = self.dbgdb.get_symbol("calloc._calloc")?;
+            if calloc.len() == 1 {
+                self.call_intercepts
+                    .insert(calloc[0].1, |d, f| d.calloc_intercept(f));
+            }
+            let memalign = self.dbgdb.get_symbol("pmemalign._posix_memalign")?;
+            if memalign.len() == 1 {
+                self.call_intercepts.insert(memalign[0].1, |d, f| {
+                    let stop = d.memalign_intercept(f)?;
+                    d.verify_heap(f, stop)
+                });
+            }
+        }
+        let heapadd = self.dbgdb.get_symbol("_heapadd.__heapadd")?;
+        if heapadd.len() == 1 {
+            self.call_intercepts.insert(heapadd[0].1, |d, f| {
+                let stop = d.heapadd_intercept(f)?;
+                d.verify_heap(f, stop)
+            });
+        }
+        let memavail = self.dbgdb.get_symbol("_heapmemavail.__heapmemavail")?;
+        if memavail.len() == 1 {
+            self.call_intercepts
+                .insert(memavail[0].1, |d, f| d.heap_avail_intercept(f, false));
+        }
+        let maxavail = self.dbgdb.get_symbol("_heapmaxavail.__heapmaxavail")?;
+        if maxavail.len() == 1 {
+            self.call_intercepts
+                .insert(maxavail[0].1, |d, f| d.heap_avail_intercept(f, true));
+        }
+        for (name, func) in CHECKED_CALLS {
+            let sym = self.dbgdb.get_symbol(name)?;
+            if sym.len() == 1 {
+                self.call_intercepts.insert(sym[0].1, func);
+            }
+        }
         Ok(())
     }
 
@@ -50,80 +160,159 @@ impl Debugger {
 
     */
     fn malloc_intercept(&mut self, ret: bool) -> Result<Option<StopReason>> {
+        if let Some(AllocWrapper::Memalign { fail, .. }) = &self.alloc_wrapper {
+            // posix_memalign carves up the block itself, we track its result
+            if !ret && *fail {
+                self.skip_with_null();
+            }
+            return Ok(None);
+        }
         if ret {
             // return from malloc, we know the address now
-            self.privileged_mode = false;
+            self.privileged_depth = self.privileged_depth.saturating_sub(1);
+            // malloc called by realloc is logged as part of the realloc
+            let inner = self.privileged_depth > 0;
             let addr = Self::ac_xr();
             if addr == 0 {
                 // malloc returned null
+                if !inner {
+                    self.heap_stats.failed += 1;
+                }
                 return Ok(None);
             }
-            let new_block = if let Some(hb) = self.heap_blocks.get(&0) {
-                (hb.alloc_addr, hb.size)
-            } else {
+            // replace the temporary 0 block
+            let Some(mut hb) = self.heap_blocks.remove(&0) else {
                 bail!("missing 0 heap block");
             };
-            let hb = HeapBlock {
-                addr,
-                size: new_block.1,
-                alloc_addr: new_block.0,
-                realloc_size: None,
-            };
-            // delete the temporary 0 block
-            self.heap_blocks.remove(&0);
+            hb.addr = addr;
+            let (size, redzone) = (hb.size, hb.redzone);
+            let (alloc_num, alloc_addr) = (hb.alloc_num, hb.alloc_addr);
             self.heap_blocks.insert(addr, hb);
+            if !inner {
+                self.record_heap_event(HeapOp::Malloc, alloc_num, addr, size, alloc_addr);
+            }
 
             // now update the shadow memory
             let shadow = Cpu::get_shadow();
-            for i in addr..addr + new_block.1 {
+            for i in addr..addr + size {
                 shadow[i as usize] |= ShadowFlags::READ | ShadowFlags::WRITE;
             }
+            for i in addr + size..addr + size + redzone {
+                shadow[i as usize] = ShadowFlags::empty();
+            }
             trace!("malloc ret {:04x}", addr);
         } else {
             // at the time of call to malloc we do not know the address
             // so create a temporary entry with addr = 0
-            let size = Self::ac_xr();
+            let mut size = Self::ac_xr();
+            if self.privileged_depth == 0 {
+                // realloc has already counted this one
+                self.heap_alloc_count += 1;
+                if self.inject_failure(size) {
+                    return Ok(self.check_heap_break());
+                }
+            }
+            let redzone = if self.privileged_depth > 0 {
+                // called from realloc, which has already added the red zone
+                let redzone = self.redzone_size();
+                size = size.saturating_sub(redzone);
+                redzone
+            } else {
+                self.add_redzone(size)
+            };
+            // calloc's caller is more interesting than calloc
+            let (alloc_addr, alloc_stack) = match &self.alloc_wrapper {
+                Some(AllocWrapper::Calloc {
+                    alloc_addr,
+                    alloc_stack,
+                }) => (*alloc_addr, alloc_stack.clone()),
+                _ => (Cpu::read_pc(), self.alloc_stack()),
+            };
             let hb = HeapBlock {
                 addr: 0,
                 size,
-                alloc_addr: Cpu::read_pc(),
+                alloc_num: self.heap_alloc_count,
+                alloc_addr,
+                alloc_stack,
+                redzone,
                 realloc_size: None,
+                free_addr: None,
+                free_stack: Vec::new(),
             };
             trace!("malloc call {} @ {:04x}", size, hb.alloc_addr);
             self.heap_blocks.insert(0, hb);
 
             // malloc is privileged - it can write to unalloacted memory
 
-            self.privileged_mode = true;
+            self.privileged_depth += 1;
+            if self.privileged_depth == 1 {
+                return Ok(self.check_heap_break());
+            }
         };
 
         Ok(None)
     }
 
     fn free_intercept(&mut self, ret: bool) -> Result<Option<StopReason>> {
-        if !ret {
+        if let Some(AllocWrapper::Memalign { .. }) = &self.alloc_wrapper {
+            // posix_memalign frees the unaligned ends of its block
+            return Ok(None);
+        }
+        if ret {
+            self.privileged_depth = self.privileged_depth.saturating_sub(1);
+        } else {
+            // free is privileged - it writes its free list links into the block
+            let inner = self.privileged_depth > 0;
+            self.privileged_depth += 1;
             let addr = Self::ac_xr();
             if addr == 0 {
                 // free of null
                 return Ok(None);
             }
-            let old = if let Some(hb) = self.heap_blocks.get(&addr) {
-                (hb.size, hb.addr)
+            let old = if let Some(hb) = self.heap_blocks.remove(&addr) {
+                hb
             } else {
                 // not found -> double or invalid free
                 if self.enable_heap_check {
-                    return Ok(Some(StopReason::Bug(BugType::HeapCheck)));
+                    return self.trap(Cpu::read_pc(), BugType::HeapCheck);
                 } else {
                     return Ok(None);
                 }
             };
-            self.heap_blocks.remove(&addr);
-
             // update the shadow to show that this is free, naked memory
             let shadow = Cpu::get_shadow();
-            for i in addr..addr + old.0 {
+            for i in addr..addr + old.size {
                 shadow[i as usize] = ShadowFlags::empty();
             }
+            let freed = HeapBlock {
+                free_addr: Some(Cpu::read_pc()),
+                free_stack: self.alloc_stack(),
+                ..old
+            };
+            if !inner {
+                let (alloc_num, size) = (freed.alloc_num, freed.size);
+                self.record_heap_event(HeapOp::Free, alloc_num, addr, size, Cpu::read_pc());
+            }
+            let quarantine_size = self.quarantine_budget();
+            if quarantine_size == 0 {
+                self.remember_freed(freed);
+                return Ok(None);
+            }
+
+            // put the block in quarantine, so the allocator cannot hand it out
+            // again straight away. Instead of this block free gets the oldest
+            // one in quarantine (if it is over budget) or NULL (a no-op)
+            self.quarantine_bytes += freed.size as usize;
+            self.quarantine.push_back(freed);
+            let mut release = 0;
+            if self.quarantine_bytes > quarantine_size as usize {
+                let oldest = self.quarantine.pop_front().unwrap();
+                self.quarantine_bytes -= oldest.size as usize;
+                release = oldest.addr;
+                self.remember_freed(oldest);
+            }
+            Cpu::write_ac(release as u8);
+            Cpu::write_xr((release >> 8) as u8);
         }
 
         Ok(None)
@@ -135,30 +324,29 @@ impl Debugger {
             // - it returned null - original block still ok
             // - it returned a new address - all work was done via malloc and free
             // - it returned the same address - we need to extend the shadow
-            self.privileged_mode = false;
+            self.privileged_depth = self.privileged_depth.saturating_sub(1);
             let addr = Self::ac_xr();
             trace!("realloc ret {:04x}", addr);
+            let from = self.realloc_from.take();
             if addr == 0 {
                 // realloc returned null
+                if from.is_some() {
+                    self.heap_stats.failed += 1;
+                }
                 return Ok(None);
             }
             if let Some(hb) = self.heap_blocks.get_mut(&addr) {
-                if let Some(sz) = hb.realloc_size {
+                if let Some(sz) = hb.realloc_size.take() {
                     // case 3 - same address
                     let orig_size = hb.size;
                     hb.size = sz;
                     let shadow = Cpu::get_shadow();
-                    if sz < orig_size {
-                        // realloc to smaller size
-                        // update the shadow to show that this is free
-
-                        for i in addr + sz..addr + orig_size {
-                            shadow[i as usize] = ShadowFlags::empty();
-                        }
-                    } else {
-                        for i in addr + orig_size..addr + hb.size {
-                            shadow[i as usize] |= ShadowFlags::READ | ShadowFlags::WRITE;
-                        }
+                    // everything past the new end is free or red zone
+                    for i in addr + sz..addr + orig_size.max(sz) + hb.redzone {
+                        shadow[i as usize] = ShadowFlags::empty();
+                    }
+                    for i in addr + orig_size..addr + sz {
+                        shadow[i as usize] |= ShadowFlags::READ | ShadowFlags::WRITE;
                     }
                 } else {
                     // case 2 - new address
@@ -166,9 +354,15 @@ impl Debugger {
                     // nothing to do
                 }
             } else {
-                // realloc returned a new address but malloc didnt see it!
-                panic!("realloc returns non heap block");
+                // realloc of something that was not a live heap block, that
+                // has already been reported when it was called
+                trace!("realloc returns non heap block {:04x}", addr);
             };
+            if let (Some((from, old_size)), Some(hb)) = (from, self.heap_blocks.get(&addr)) {
+                let (alloc_num, size, pc) = (hb.alloc_num, hb.size, hb.alloc_addr);
+                let op = HeapOp::Realloc { from, old_size };
+                self.record_heap_event(op, alloc_num, addr, size, pc);
+            }
         } else {
             let addr = Self::read_arg(0);
             let size = Self::ac_xr();
@@ -183,34 +377,441 @@ impl Debugger {
                 return Ok(None);
             }
             trace!("realloc call {} @ {:04x}", size, addr);
+            let alloc_stack = self.alloc_stack();
+            let redzone = self.redzone_size();
+            let alloc_num = self.heap_alloc_count + 1;
+            if let Some(hb) = self.heap_blocks.get(&addr) {
+                self.heap_alloc_count = alloc_num;
+                if self.inject_failure(size.saturating_sub(hb.size)) {
+                    // realloc pops the block pointer itself, so we must too
+                    // the block is untouched, as when realloc really fails
+                    let sp65_addr = Cpu::get_sp65_addr() as u16;
+                    let sp65 = Cpu::read_word(sp65_addr);
+                    Cpu::write_word(sp65_addr, sp65.wrapping_add(2));
+                    return Ok(self.check_heap_break());
+                }
+            }
             if let Some(hb) = self.heap_blocks.get_mut(&addr) {
+                self.realloc_from = Some((addr, hb.size));
+                hb.alloc_num = alloc_num;
                 hb.realloc_size = Some(size);
                 hb.alloc_addr = Cpu::read_pc();
+                hb.alloc_stack = alloc_stack;
+                hb.redzone = redzone;
             } else {
                 // not found -> realloc of non heap block
                 if self.enable_heap_check {
-                    return Ok(Some(StopReason::Bug(BugType::HeapCheck)));
+                    return self.trap(Cpu::read_pc(), BugType::HeapCheck);
                 } else {
                     return Ok(None);
                 }
             };
 
+            // ask for the red zone too. If realloc has to move the block
+            // the malloc intercept knows not to add another one
+            self.add_redzone(size);
+
             // realloc is privileged - it can write to unalloacted memory
 
-            self.privileged_mode = true;
+            self.privileged_depth += 1;
+            return Ok(self.check_heap_break());
         };
 
         Ok(None)
     }
+    fn calloc_intercept(&mut self, ret: bool) -> Result<Option<StopReason>> {
+        if ret {
+            self.alloc_wrapper = None;
+            // calloc clears the block, make sure all of it counts as written
+            let addr = Self::ac_xr();
+            if let Some(hb) = self.heap_blocks.get(&addr) {
+                let shadow = Cpu::get_shadow();
+                for i in addr..addr + hb.size {
+                    shadow[i as usize] |= ShadowFlags::WRITTEN;
+                }
+            }
+        } else {
+            // calloc multiplies its args and calls malloc, which does the work
+            self.alloc_wrapper = Some(AllocWrapper::Calloc {
+                alloc_addr: Cpu::read_pc(),
+                alloc_stack: self.alloc_stack(),
+            });
+        }
+        Ok(None)
+    }
+    /*
+        int posix_memalign (void** memptr, size_t alignment, size_t size)
+
+        mallocs a bigger block and then frees the ends either side of the
+        aligned part using hand made block headers. We dont follow any of
+        that, just pick up the final block from *memptr
+    */
+    fn memalign_intercept(&mut self, ret: bool) -> Result<Option<StopReason>> {
+        if ret {
+            self.privileged_depth = self.privileged_depth.saturating_sub(1);
+            let Some(AllocWrapper::Memalign {
+                memptr,
+                size,
+                redzone,
+                fail,
+                alloc_num,
+                alloc_addr,
+                alloc_stack,
+            }) = self.alloc_wrapper.take()
+            else {
+                return Ok(None);
+            };
+            let addr = Cpu::read_word(memptr);
+            if Self::ac_xr() != 0 || addr == 0 {
+                // EINVAL, ENOMEM or a size of 0
+                if Self::ac_xr() != 0 && !fail {
+                    self.heap_stats.failed += 1;
+                }
+                return Ok(None);
+            }
+            let shadow = Cpu::get_shadow();
+            for i in addr..addr + size {
+                shadow[i as usize] |= ShadowFlags::READ | ShadowFlags::WRITE;
+            }
+            for i in addr + size..addr + size + redzone {
+                shadow[i as usize] = ShadowFlags::empty();
+            }
+            self.heap_blocks.insert(
+                addr,
+                HeapBlock {
+                    addr,
+                    size,
+                    alloc_num,
+                    alloc_addr,
+                    alloc_stack,
+                    redzone,
+                    realloc_size: None,
+                    free_addr: None,
+                    free_stack: Vec::new(),
+                },
+            );
+            self.record_heap_event(HeapOp::Malloc, alloc_num, addr, size, alloc_addr);
+        } else {
+            // posix_memalign(memptr, alignment, size), size is in AX and
+            // memptr is pushed first so it is the deeper one
+            let memptr = Self::read_arg(2);
+            let size = Self::ac_xr();
+            self.heap_alloc_count += 1;
+            // a failure is injected in the malloc it calls, so it
+            // returns ENOMEM by itself
+            let fail = self.should_fail(size);
+            let redzone = if fail { 0 } else { self.add_redzone(size) };
+            self.alloc_wrapper = Some(AllocWrapper::Memalign {
+                memptr,
+                size,
+                redzone,
+                fail,
+                alloc_num: self.heap_alloc_count,
+                alloc_addr: Cpu::read_pc(),
+                alloc_stack: self.alloc_stack(),
+            });
+            // it writes block headers all over its raw block
+            self.privileged_depth += 1;
+            return Ok(self.check_heap_break());
+        }
+        Ok(None)
+    }
+    /*
+        void _heapadd (void* mem, size_t size)
+
+        turns the memory into a free heap block
+    */
+    fn heapadd_intercept(&mut self, ret: bool) -> Result<Option<StopReason>> {
+        if ret {
+            self.privileged_depth = self.privileged_depth.saturating_sub(1);
+        } else {
+            let start = Self::read_arg(0);
+            let size = Self::ac_xr();
+            // cc65 ignores blocks too small to be a free block
+            if size >= MIN_FREE_BLOCK && start.checked_add(size).is_some() {
+                // it is naked heap now, only malloc can hand it out
+                let shadow = Cpu::get_shadow();
+                for i in start..start + size {
+                    shadow[i as usize] = ShadowFlags::empty();
+                }
+                self.heap_regions.push((start, size));
+            }
+            // _heapadd writes the free block header into it
+            self.privileged_depth += 1;
+        }
+        Ok(None)
+    }
+    /*
+        _heapmemavail and _heapmaxavail
+
+        red zones and the quarantine use up heap the program does not
+        know about, give it back so the program sees what it would
+        without db65
+    */
+    fn heap_avail_intercept(&mut self, ret: bool, largest: bool) -> Result<Option<StopReason>> {
+        if !ret {
+            return Ok(None);
+        }
+        let Ok(walk) = self.check_heap() else {
+            return Ok(None);
+        };
+        if !walk.errors.is_empty() {
+            return Ok(None);
+        }
+        let extra = if largest {
+            self.largest_free(&walk, true) - self.largest_free(&walk, false)
+        } else {
+            let quarantined = walk
+                .chunks
+                .iter()
+                .filter(|c| c.user.is_some_and(|u| !self.heap_blocks.contains_key(&u)))
+                .map(|c| c.size as usize)
+                .sum::<usize>();
+            let redzones = self
+                .heap_blocks
+                .values()
+                .map(|hb| hb.redzone as usize)
+                .sum::<usize>();
+            (quarantined + redzones).min(0xffff) as u16
+        };
+        let avail = Self::ac_xr().saturating_add(extra);
+        trace!("heap avail {:04x} + {}", Self::ac_xr(), extra);
+        Cpu::write_ac(avail as u8);
+        Cpu::write_xr((avail >> 8) as u8);
+        Ok(None)
+    }
+    /*  Bounds checks
+
+        The whole of every buffer the call will read or write is checked
+        before the call runs, so the bug is reported at the caller. The call
+        itself runs privileged so it does not report the same thing again
+        one byte at a time from inside the runtime
+
+        fastcall: the last arg is in AX, the others are on the c stack,
+        the first one deepest
+    */
+    fn bounds_intercept(&mut self, ret: bool, call: CheckedCall) -> Result<Option<StopReason>> {
+        if ret {
+            self.privileged_depth = self.privileged_depth.saturating_sub(1);
+            return Ok(None);
+        }
+        let outer = self.privileged_depth == 0;
+        self.privileged_depth += 1;
+        if !outer {
+            // used by the runtime itself (inside the allocator)
+            return Ok(None);
+        }
+        match self.check_call(call, Self::ac_xr()) {
+            Some(bug) => self.trap(Cpu::read_pc(), bug),
+            None => Ok(None),
+        }
+    }
+    // the first bad byte the call will touch, ax is its last argument
+    fn check_call(&self, call: CheckedCall, ax: u16) -> Option<BugType> {
+        match call {
+            CheckedCall::Memcpy | CheckedCall::Memmove => {
+                // (dest, src, n)
+                let (dest, src) = (Self::read_arg(2), Self::read_arg(0));
+                self.check_range(src, ax as u32, Access::Copy)
+                    .or_else(|| self.check_range(dest, ax as u32, Access::Write))
+            }
+            CheckedCall::Memset => self.check_range(Self::read_arg(2), ax as u32, Access::Write),
+            CheckedCall::Strcpy => {
+                // (dest, src)
+                let dest = Self::read_arg(0);
+                match self.check_string(ax, 0x10000) {
+                    (len, None) => self.check_range(dest, len + 1, Access::Write),
+                    (_, bug) => bug,
+                }
+            }
+            CheckedCall::Strcat => {
+                // (dest, src)
+                let dest = Self::read_arg(0);
+                match (
+                    self.check_string(dest, 0x10000),
+                    self.check_string(ax, 0x10000),
+                ) {
+                    ((dlen, None), (slen, None)) => {
+                        self.check_range(dest.wrapping_add(dlen as u16), slen + 1, Access::Write)
+                    }
+                    ((_, Some(bug)), _) | (_, (_, Some(bug))) => Some(bug),
+                }
+            }
+            CheckedCall::Strlen => self.check_string(ax, 0x10000).1,
+            CheckedCall::Strncpy => {
+                // (dest, src, n), reads up to n, always writes n
+                let (dest, src) = (Self::read_arg(2), Self::read_arg(0));
+                self.check_string(src, ax as u32)
+                    .1
+                    .or_else(|| self.check_range(dest, ax as u32, Access::Write))
+            }
+            CheckedCall::Fgets => {
+                // (buf, size, file), writes up to size bytes
+                let (buf, size) = (Self::read_arg(2), Self::read_arg(0) as i16);
+                if size > 0 {
+                    self.check_range(buf, size as u32, Access::Write)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+    // the first bad byte in a range the call is about to touch
+    fn check_range(&self, start: u16, len: u32, access: Access) -> Option<BugType> {
+        if len > 0 && start < self.null_guard {
+            return Some(BugType::NullDeref(start));
+        }
+        (0..len.min(0x10000))
+            .map(|i| start.wrapping_add(i as u16))
+            .find_map(|addr| self.check_access(addr, access))
+    }
+    // the length of a string (reading no more than max bytes), or the bug
+    // that reading it runs into
+    fn check_string(&self, start: u16, max: u32) -> (u32, Option<BugType>) {
+        if max > 0 && start < self.null_guard {
+            return (0, Some(BugType::NullDeref(start)));
+        }
+        for i in 0..max.min(0x10000) {
+            let addr = start.wrapping_add(i as u16);
+            if let Some(bug) = self.check_access(addr, Access::Read) {
+                return (i, Some(bug));
+            }
+            if Cpu::read_byte(addr) == 0 {
+                return (i, None);
+            }
+        }
+        (max, None)
+    }
+    // same classification as the memcheck in execute, but from the
+    // shadow rather than from an access the cpu has just made
+    fn check_access(&self, addr: u16, access: Access) -> Option<BugType> {
+        let flags = Cpu::get_shadow()[addr as usize];
+        if self.enable_heap_check && self.in_redzone(addr) {
+            Some(BugType::HeapOverflow(addr))
+        } else if self.enable_heap_check && self.in_quarantine(addr) {
+            Some(BugType::UseAfterFree(addr))
+        } else if access != Access::Write
+            && self.enable_mem_check
+            && !flags.contains(ShadowFlags::WRITTEN)
+            && self.in_dead_stack(addr)
+        {
+            Some(BugType::DeadStack(addr))
+        } else if access == Access::Write {
+            (self.enable_seg_check && !flags.contains(ShadowFlags::WRITE))
+                .then_some(BugType::SegCheck(addr))
+        } else if !self.enable_mem_check {
+            None
+        } else if !self.uninit_track || !flags.contains(ShadowFlags::READ) {
+            (!flags.contains(ShadowFlags::WRITTEN)).then_some(BugType::Memcheck(addr))
+        } else if access == Access::Read {
+            // the string functions test every byte they read
+            self.mem_taint(addr)
+                .map(|from| BugType::UninitValue(UninitUse::Branch, from))
+        } else {
+            // copying undefined bytes is fine, the copy stays undefined
+            None
+        }
+    }
+    // 'heap break' stops when this allocation number is reached
+    fn check_heap_break(&self) -> Option<StopReason> {
+        if self.heap_break == Some(self.heap_alloc_count) {
+            Some(StopReason::HeapBreak(self.heap_alloc_count))
+        } else {
+            None
+        }
+    }
+    // remember a really freed block for bug reports
+    fn remember_freed(&mut self, hb: HeapBlock) {
+        if self.freed_blocks.len() == FREED_HISTORY {
+            self.freed_blocks.pop_front();
+        }
+        self.freed_blocks.push_back(hb);
+    }
+    // red zones are only checked with heap checks on, without them
+    // they would just waste heap
+    pub(crate) fn redzone_size(&self) -> u16 {
+        if self.enable_heap_check {
+            self.heap_redzone
+        } else {
+            0
+        }
+    }
+    // the same goes for the quarantine, nothing reports use after free
+    pub(crate) fn quarantine_budget(&self) -> u16 {
+        if self.enable_heap_check {
+            self.quarantine_size
+        } else {
+            0
+        }
+    }
+    // grow the size the allocator is about to be called with (in AX)
+    // so there is a gap after the block, returns the size of the gap
+    fn add_redzone(&self, size: u16) -> u16 {
+        let redzone = self.redzone_size();
+        if redzone == 0 || size == 0 {
+            return 0;
+        }
+        let Some(padded) = size.checked_add(redzone) else {
+            return 0;
+        };
+        Cpu::write_ac(padded as u8);
+        Cpu::write_xr((padded >> 8) as u8);
+        redzone
+    }
+    // the callers of the allocator, the allocator's own frame is
+    // already on the stack (we are at its jsr) so skip it
+    fn alloc_stack(&self) -> Vec<u16> {
+        self.call_stack().into_iter().skip(1).collect()
+    }
     fn ac_xr() -> u16 {
         let ac = Cpu::read_ac();
         let xr = Cpu::read_xr();
         (xr as u16) << 8 | (ac as u16)
     }
+    // the word at sp65+offset on the c stack
     fn read_arg(offset: u16) -> u16 {
         let sp65_addr = Cpu::get_sp65_addr() as u16;
-        let sp65 = Cpu::read_word(sp65_addr + offset);
+        let sp65 = Cpu::read_word(sp65_addr);
 
-        Cpu::read_word(sp65)
+        Cpu::read_word(sp65.wrapping_add(offset))
+    }
+}
+#[test]
+fn memcpy_overflow() {
+    let _cpu = Cpu::test_lock();
+    let mut dbg = Debugger::new();
+    dbg.enable_heap_check = true;
+    dbg.enable_mem_check = true;
+    dbg.enable_seg_check = true;
+    // a 10 byte heap block at $6000 and its red zone
+    dbg.heap_blocks.insert(
+        0x6000,
+        HeapBlock {
+            addr: 0x6000,
+            size: 10,
+            alloc_num: 1,
+            alloc_addr: 0,
+            alloc_stack: Vec::new(),
+            redzone: 8,
+            realloc_size: None,
+            free_addr: None,
+            free_stack: Vec::new(),
+        },
+    );
+    for flags in &mut Cpu::get_shadow()[0x6000..0x6100] {
+        *flags |= ShadowFlags::READ | ShadowFlags::WRITE | ShadowFlags::WRITTEN;
     }
+    // memcpy(dest, src, n): sp65 points at src, dest is above it. The zero
+    // page word after sp65 points somewhere harmless
+    Cpu::sp65_addr(0x60);
+    Cpu::write_word(0x60, 0x6080);
+    Cpu::write_word(0x62, 0x60c0);
+    Cpu::write_word(0x6080, 0x6040);
+    Cpu::write_word(0x6082, 0x6000);
+
+    assert!(dbg.check_call(CheckedCall::Memcpy, 10).is_none());
+    assert!(matches!(
+        dbg.check_call(CheckedCall::Memcpy, 11),
+        Some(BugType::HeapOverflow(0x600a))
+    ));
 }
@@ -0,0 +1,230 @@
+/*
+Lints, warnings about classic 6502 pitfalls. They are not bugs as such
+(the program may well mean it) so they never stop execution, each one is
+printed once per address. All off by default, see 'about traps'
+
+  jmpind  - jmp ($xxFF), the NMOS 6502 takes the high byte from $xx00
+  decimal - calling the c runtime (or a c function) with the decimal flag set
+  brk     - a brk instruction executed
+  pvgap   - reading the paravirt hook addresses, there is no memory there
+  midinsn - a jump or branch into the middle of an instruction that has
+            been executed. The bit skip trick ($2C / $24) is allowed
+*/
+use std::fmt;
+
+use super::{
+    core::Debugger,
+    cpu::{Cpu, ShadowFlags, Status},
+    paravirt::{PARAVIRT_BASE, PV_NAMES},
+    taint::insn_len,
+};
+use anyhow::{bail, Result};
+use bitflags::bitflags;
+use util::say;
+
+bitflags! {
+    #[derive(Copy, Clone, Default, Debug, PartialEq, Eq)]
+    pub struct Lints: u8 {
+        const JMP_IND = 0b0000_0001;
+        const DECIMAL = 0b0000_0010;
+        const BRK =     0b0000_0100;
+        const PV_GAP =  0b0000_1000;
+        const MID_INSN = 0b0001_0000;
+    }
+}
+// same order as the flags
+const LINT_NAMES: [(&str, Lints); 5] = [
+    ("jmpind", Lints::JMP_IND),
+    ("decimal", Lints::DECIMAL),
+    ("brk", Lints::BRK),
+    ("pvgap", Lints::PV_GAP),
+    ("midinsn", Lints::MID_INSN),
+];
+
+impl Lints {
+    // 'all', 'none', a list of names or +name / -name to change the
+    // current set
+    pub fn parse(current: Lints, list: &str) -> Result<Lints> {
+        let mut lints = current;
+        let mut replaced = false;
+        for item in list.split(',').map(str::trim) {
+            let (op, name) = match item.chars().next() {
+                Some(c @ ('+' | '-')) => (Some(c), &item[1..]),
+                _ => (None, item),
+            };
+            let flags = match name {
+                "all" => Lints::all(),
+                "none" => Lints::empty(),
+                _ => match LINT_NAMES.iter().find(|(n, _)| *n == name) {
+                    Some((_, f)) => *f,
+                    None => bail!("unknown lint '{}'", name),
+                },
+            };
+            match op {
+                Some('+') => lints |= flags,
+                Some(_) => lints -= flags,
+                None => {
+                    if !replaced {
+                        lints = Lints::empty();
+                        replaced = true;
+                    }
+                    lints |= flags;
+                }
+            }
+        }
+        Ok(lints)
+    }
+}
+impl fmt::Display for Lints {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names = LINT_NAMES
+            .iter()
+            .filter(|(_, l)| self.contains(*l))
+            .map(|(n, _)| *n)
+            .collect::<Vec<_>>();
+        if names.is_empty() {
+            write!(f, "none")
+        } else {
+            write!(f, "{}", names.join(","))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    JmpIndWrap(u16),  // the pointer
+    DecimalCall(u16), // the function called
+    Brk,
+    PvGapRead(u16),
+    MidInsn(u16), // where it went
+}
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Lint::JmpIndWrap(ptr) => write!(
+                f,
+                "jmp (${:04x}) takes its high byte from ${:04x} on a 6502",
+                ptr,
+                ptr & 0xff00
+            ),
+            Lint::DecimalCall(addr) => {
+                write!(f, "call to ${:04x} with the decimal flag set", addr)
+            }
+            Lint::Brk => write!(f, "brk executed"),
+            Lint::PvGapRead(addr) => write!(f, "read of paravirt hook address ${:04x}", addr),
+            Lint::MidInsn(addr) => {
+                write!(f, "jump into the middle of an instruction -> ${:04x}", addr)
+            }
+        }
+    }
+}
+
+impl Debugger {
+    // the lints that look at the instruction before it runs
+    pub(crate) fn lint_before(&mut self, pc: u16) -> Result<()> {
+        if self.lints.is_empty() {
+            return Ok(());
+        }
+        let lint = match Cpu::read_byte(pc) {
+            // the loader header says which cpu, 0 is the 6502
+            0x6c if self.lints.contains(Lints::JMP_IND) && Cpu::get_cpu() == 0 => {
+                let ptr = Cpu::read_word(pc.wrapping_add(1));
+                (ptr & 0xff == 0xff).then_some(Lint::JmpIndWrap(ptr))
+            }
+            0x20 if self.lints.contains(Lints::DECIMAL)
+                && Status::from_bits_truncate(Cpu::read_sr()).contains(Status::DECIMAL) =>
+            {
+                let addr = Cpu::read_word(pc.wrapping_add(1));
+                self.is_runtime(addr).then_some(Lint::DecimalCall(addr))
+            }
+            0x00 if self.lints.contains(Lints::BRK) => Some(Lint::Brk),
+            _ => None,
+        };
+        if let Some(lint) = lint {
+            self.lint(pc, lint)?;
+        }
+        Ok(())
+    }
+    // and the ones that need to see what it did
+    pub(crate) fn lint_after(&mut self, pc: u16, opcode: u8) -> Result<()> {
+        if self.lints.is_empty() {
+            return Ok(());
+        }
+        let len = insn_len(opcode);
+        let pv_hooks = PARAVIRT_BASE..PARAVIRT_BASE + PV_NAMES.len() as u16;
+        if self.lints.contains(Lints::PV_GAP) && !pv_hooks.contains(&pc) {
+            // the instruction's own bytes are read too
+            let own = pc..pc.wrapping_add(len);
+            let hits = Cpu::get_memhits();
+            let gap = hits[..Cpu::get_memhitcount().min(8) as usize]
+                .iter()
+                .find(|(write, addr)| !write && pv_hooks.contains(addr) && !own.contains(addr));
+            if let Some((_, addr)) = gap {
+                self.lint(pc, Lint::PvGapRead(*addr))?;
+            }
+        }
+        if self.lints.contains(Lints::MID_INSN) {
+            let shadow = Cpu::get_shadow();
+            let next = Cpu::read_pc();
+            if next != pc.wrapping_add(len) && shadow[next as usize].contains(ShadowFlags::OPERAND)
+            {
+                self.lint(pc, Lint::MidInsn(next))?;
+            }
+            // bit abs / bit zp is used to skip the next instruction, that
+            // one gets jumped to on purpose
+            if opcode != 0x2c && opcode != 0x24 {
+                for i in 1..len {
+                    shadow[pc.wrapping_add(i) as usize] |= ShadowFlags::OPERAND;
+                }
+            }
+        }
+        Ok(())
+    }
+    // a c function or anything in the runtime library
+    fn is_runtime(&self, addr: u16) -> bool {
+        self.c_callable.contains(&addr)
+            || self
+                .module_chunk(addr)
+                .is_some_and(|m| self.lib_modules.contains(&m.module))
+    }
+    fn lint(&mut self, pc: u16, lint: Lint) -> Result<()> {
+        if self.lint_seen.insert((pc, lint)) {
+            say!("Lint: {} at {}", lint, self.location_string(pc)?);
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn parse_lints() {
+    let none = Lints::empty();
+    assert_eq!(Lints::parse(none, "all").unwrap(), Lints::all());
+    assert_eq!(Lints::parse(Lints::all(), "none").unwrap(), none);
+    assert_eq!(
+        Lints::parse(none, "brk, jmpind").unwrap(),
+        Lints::BRK | Lints::JMP_IND
+    );
+    // a plain list replaces the current set, +/- change it
+    assert_eq!(Lints::parse(Lints::DECIMAL, "brk").unwrap(), Lints::BRK);
+    assert_eq!(
+        Lints::parse(Lints::DECIMAL, "+brk").unwrap(),
+        Lints::DECIMAL | Lints::BRK
+    );
+    assert_eq!(
+        Lints::parse(Lints::all(), "-pvgap,-midinsn").unwrap(),
+        Lints::JMP_IND | Lints::DECIMAL | Lints::BRK
+    );
+    assert_eq!(
+        Lints::parse(none, "all,-brk").unwrap(),
+        Lints::all() - Lints::BRK
+    );
+    assert_eq!(Lints::all().to_string(), "jmpind,decimal,brk,pvgap,midinsn");
+    assert_eq!(none.to_string(), "none");
+}
+
+#[test]
+fn parse_unknown() {
+    assert!(Lints::parse(Lints::empty(), "brk,bogus").is_err());
+    assert!(Lints::parse(Lints::empty(), "+bogus").is_err());
+    assert!(Lints::parse(Lints::empty(), "").is_err());
+}
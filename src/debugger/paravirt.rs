@@ -12,6 +12,7 @@ use core::panic;
 use once_cell::sync::Lazy;
 use std::{
     collections::HashMap,
+    fmt,
     fs::{File, OpenOptions},
     io::{stderr, stdout, Read, Write},
 };
@@ -20,7 +21,7 @@ use std::{
 // static r/w global - so it needs unsafe code
 
 static mut PV_FILES: Lazy<HashMap<u16, File>> = Lazy::new(HashMap::new);
-const PARAVIRT_BASE: u16 = 0xFFF4;
+pub const PARAVIRT_BASE: u16 = 0xFFF4;
 static PV_HOOKS: [fn(); 6] = [
     ParaVirt::pv_open,
     ParaVirt::pv_close,
@@ -29,9 +30,131 @@ static PV_HOOKS: [fn(); 6] = [
     ParaVirt::pv_args,
     ParaVirt::pv_exit,
 ];
+// same order as PV_HOOKS
+pub const PV_NAMES: [&str; 6] = ["open", "close", "read", "write", "args", "exit"];
+
+// the arguments of a PV call, decoded before the call is made
+#[derive(Debug, Clone)]
+pub enum PvArgs {
+    Open {
+        name: String,
+        flags: u16,
+        mode: Option<u16>,
+    },
+    Close {
+        fd: u16,
+    },
+    Read {
+        fd: u16,
+        buf: u16,
+        count: u16,
+    },
+    Write {
+        fd: u16,
+        buf: u16,
+        count: u16,
+    },
+    Args {
+        argv: u16,
+    },
+    Exit {
+        code: u8,
+    },
+}
+#[derive(Debug, Clone)]
+pub struct PvCall {
+    pub args: PvArgs,
+    pub result: Option<u16>, // None if we stopped before the call
+}
+
+impl fmt::Display for PvCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.args {
+            PvArgs::Open { name, flags, mode } => {
+                write!(f, "open(\"{}\", flags=${:02x}", name, flags)?;
+                if let Some(m) = mode {
+                    write!(f, ", mode=${:02x}", m)?;
+                }
+                write!(f, ")")?;
+            }
+            PvArgs::Close { fd } => write!(f, "close(fd={})", fd)?,
+            PvArgs::Read { fd, buf, count } => {
+                write!(f, "read(fd={}, buf=${:04x}, count={})", fd, buf, count)?
+            }
+            PvArgs::Write { fd, buf, count } => {
+                write!(f, "write(fd={}, buf=${:04x}, count={})", fd, buf, count)?
+            }
+            PvArgs::Args { argv } => write!(f, "args(argv=${:04x})", argv)?,
+            PvArgs::Exit { code } => write!(f, "exit({})", code)?,
+        }
+        if let Some(res) = self.result {
+            write!(f, " = {}", res as i16)?;
+        }
+        Ok(())
+    }
+}
 
 pub struct ParaVirt;
 impl ParaVirt {
+    // if the instruction at pc is a jsr or jmp into the PV hooks
+    // return the hook address
+    pub fn pv_target(pc: u16) -> Option<u16> {
+        let target = match Cpu::read_byte(pc) {
+            0x20 | 0x4c => Cpu::read_word(pc.wrapping_add(1)),
+            0x6c => Cpu::read_word(Cpu::read_word(pc.wrapping_add(1))),
+            _ => return None,
+        };
+        if target >= PARAVIRT_BASE && target < PARAVIRT_BASE + PV_HOOKS.len() as u16 {
+            Some(target)
+        } else {
+            None
+        }
+    }
+
+    // decode the arguments of the hook at 'target' without popping them
+    // mirrors what the pv_xxx functions below do
+    pub fn peek_args(target: u16) -> PvArgs {
+        let sp65 = Cpu::read_word(Cpu::get_sp65_addr() as u16);
+        let arg = |offset: u16| Cpu::read_word(sp65.wrapping_add(offset));
+        match target - PARAVIRT_BASE {
+            0 => {
+                let extra = (Cpu::read_yr() as u16).wrapping_sub(4);
+                let mut name = Vec::new();
+                let mut addr = arg(extra + 2);
+                loop {
+                    let c = Cpu::read_byte(addr);
+                    if c == 0 || name.len() > 256 {
+                        break;
+                    }
+                    name.push(c);
+                    addr = addr.wrapping_add(1);
+                }
+                PvArgs::Open {
+                    name: String::from_utf8_lossy(&name).to_string(),
+                    flags: arg(extra),
+                    mode: if extra >= 2 { Some(arg(0)) } else { None },
+                }
+            }
+            1 => PvArgs::Close { fd: Self::get_ax() },
+            2 => PvArgs::Read {
+                fd: arg(2),
+                buf: arg(0),
+                count: Self::get_ax(),
+            },
+            3 => PvArgs::Write {
+                fd: arg(2),
+                buf: arg(0),
+                count: Self::get_ax(),
+            },
+            4 => PvArgs::Args {
+                argv: Self::get_ax(),
+            },
+            _ => PvArgs::Exit {
+                code: Cpu::read_ac(),
+            },
+        }
+    }
+
     fn pop_arg(incr: u16) -> u16 {
         let sp65_addr = Cpu::get_sp65_addr();
         let sp65 = Cpu::read_word(sp65_addr as u16);
@@ -53,7 +176,7 @@ impl ParaVirt {
         Cpu::write_xr(((val >> 8) & 0xff) as u8);
     }
 
-    fn get_ax() -> u16 {
+    pub fn get_ax() -> u16 {
         let ac = Cpu::read_ac() as u16;
         let xr = Cpu::read_xr() as u16;
         ac | (xr << 8)
@@ -4,15 +4,20 @@
 */
 
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, HashMap, VecDeque},
     path::{Path, PathBuf},
 };
 
 use dbgdata::debugdb::{Segment, SourceFile, SourceInfo, Symbol};
 
 use super::{
-    core::{BreakPoint, Debugger, HeapBlock, StackFrame, WatchPoint},
-    cpu::Cpu,
+    core::{BreakPoint, CatchPoint, Debugger, HeapBlock, StackFrame, StackUsage, WatchPoint},
+    cpu::{Cpu, ShadowFlags},
+    execute::{BugReport, TrapMode},
+    heap::{FailPolicy, HeapEvent, HeapStats},
+    lint::Lints,
+    suppress::Suppression,
+    taint::indirect_pointer,
 };
 use anyhow::{bail, Result};
 impl Debugger {
@@ -25,6 +30,56 @@ impl Debugger {
     pub fn enable_heap_check(&mut self, enable: bool) {
         self.enable_heap_check = enable;
     }
+    pub fn set_quarantine_size(&mut self, size: u16) {
+        self.quarantine_size = size;
+    }
+    pub fn set_heap_verify(&mut self, enable: bool) {
+        self.heap_verify = enable;
+    }
+    pub fn set_uninit_track(&mut self, enable: bool) {
+        self.uninit_track = enable;
+    }
+    pub fn set_null_guard(&mut self, size: u16) {
+        self.null_guard = size;
+    }
+    pub fn set_lints(&mut self, list: &str) -> Result<()> {
+        self.lints = Lints::parse(self.lints, list)?;
+        Ok(())
+    }
+    pub fn set_smc_allow(&mut self, modules: &str) {
+        self.smc_allow = match modules {
+            "none" | "" => Vec::new(),
+            _ => modules.split(',').map(|m| m.trim().to_string()).collect(),
+        };
+    }
+    // has the program changed any of the code bytes from addr on
+    pub fn is_patched(&self, addr: u16, len: u8) -> bool {
+        let shadow = Cpu::get_shadow();
+        (0..len as u16)
+            .any(|i| shadow[addr.wrapping_add(i) as usize].contains(ShadowFlags::PATCHED))
+    }
+    pub fn set_heap_redzone(&mut self, size: u16) {
+        self.heap_redzone = size;
+    }
+    pub fn enable_seg_check(&mut self, enable: bool) {
+        self.enable_seg_check = enable;
+    }
+    pub fn set_trap_mode(&mut self, mode: TrapMode) {
+        self.trap_mode = mode;
+    }
+    pub fn get_bug_log(&self) -> &[BugReport] {
+        &self.bug_log
+    }
+    pub fn get_bug_pc(&self) -> Option<u16> {
+        self.bug_pc
+    }
+    // where the pointer used by the instruction at the bug is
+    pub fn get_bug_pointer(&self) -> Option<u16> {
+        indirect_pointer(self.bug_pc?).map(|(ptr, _)| ptr)
+    }
+    pub fn get_suppressions(&self) -> &[Suppression] {
+        &self.suppressions
+    }
     pub fn set_cc65_dir(&mut self, dir: &PathBuf) -> Result<()> {
         if !dir.exists() {
             bail!("{:?} does not exist", dir);
@@ -62,9 +117,44 @@ impl Debugger {
     pub fn get_watches(&self) -> Result<&BTreeMap<u16, WatchPoint>> {
         Ok(&self.watch_points)
     }
+    pub fn get_catches(&self) -> Result<&BTreeMap<u16, CatchPoint>> {
+        Ok(&self.catch_points)
+    }
     pub fn get_heap_blocks(&self) -> &HashMap<u16, HeapBlock> {
         &self.heap_blocks
     }
+    pub fn get_heap_events(&self) -> &VecDeque<HeapEvent> {
+        &self.heap_events
+    }
+    pub fn set_heap_break(&mut self, num: Option<usize>) {
+        self.heap_break = num;
+    }
+    pub fn get_heap_break(&self) -> Option<usize> {
+        self.heap_break
+    }
+    pub fn set_heap_fail(&mut self, policy: Option<FailPolicy>) {
+        if let Some(FailPolicy::Random { seed, .. }) = policy {
+            self.heap_fail_rng = seed;
+        }
+        self.heap_fail = policy;
+    }
+    pub fn get_heap_fail(&self) -> Option<FailPolicy> {
+        self.heap_fail
+    }
+    pub fn get_heap_stats(&self) -> &HeapStats {
+        &self.heap_stats
+    }
+    pub fn get_stack_usage(&self) -> StackUsage {
+        let top = self.stack_region.map(|(_, top)| top);
+        StackUsage {
+            c_used: match top {
+                Some(top) if self.sp65_floor != 0 => top.saturating_sub(self.sp65_floor),
+                _ => 0,
+            },
+            c_size: self.stack_region.map(|(bottom, top)| top - bottom),
+            hw_used: 0xff - self.hw_sp_floor as u16,
+        }
+    }
     pub fn get_chunk(&self, addr: u16, mut len: u16) -> Result<Vec<u8>> {
         let mut v = Vec::new();
         let max_add = addr.saturating_add(len);
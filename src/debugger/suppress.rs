@@ -0,0 +1,268 @@
+/*
+Bug suppressions, a cut down version of valgrind suppression files
+
+{
+   name of the suppression
+   Memcheck
+   fun:ldaxi*
+   ...
+   file:*.s
+}
+
+The second line is the bug type (or '*' for any). Then follows a
+list of frame patterns, the first one matches where the bug happened,
+the next one its caller and so on. Each is one of
+
+  fun:<glob>   the function (code label) the frame is in
+  mod:<glob>   the module (object file) the frame is in
+  file:<glob>  the source file (c or asm) the frame is in
+  ...          matches zero or more frames
+
+Frames beyond the end of the pattern list are not checked.
+Globs support '*' and '?'. '#' starts a comment line.
+*/
+use std::{fs::File, io::Read, path::Path};
+
+use anyhow::{bail, Result};
+use regex::Regex;
+
+use super::{core::Debugger, execute::BugType};
+
+#[derive(Debug)]
+enum FramePattern {
+    Fun(Regex),
+    Module(Regex),
+    File(Regex),
+    Any,
+}
+#[derive(Debug)]
+pub struct Suppression {
+    pub name: String,
+    pub kind: String,
+    frames: Vec<FramePattern>,
+    pub hits: usize,
+}
+// what we know about one frame of the call stack
+#[derive(Debug, Default)]
+struct FrameInfo {
+    fun: String,
+    module: String,
+    file: String,
+}
+
+fn glob_to_regex(glob: &str) -> Result<Regex> {
+    let mut re = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => re.push_str(".*"),
+            '?' => re.push('.'),
+            _ => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+    Ok(Regex::new(&re)?)
+}
+
+impl Suppression {
+    pub fn parse(text: &str) -> Result<Vec<Suppression>> {
+        let mut sups = Vec::new();
+        let mut current: Option<Suppression> = None;
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let line_no = i + 1;
+            match (line, current.as_mut()) {
+                ("{", None) => {
+                    current = Some(Suppression {
+                        name: String::new(),
+                        kind: String::new(),
+                        frames: Vec::new(),
+                        hits: 0,
+                    })
+                }
+                ("}", Some(sup)) => {
+                    if sup.kind.is_empty() {
+                        bail!(
+                            "line {}: suppression '{}' has no bug type",
+                            line_no,
+                            sup.name
+                        );
+                    }
+                    sups.push(current.take().unwrap());
+                }
+                (_, None) => bail!("line {}: expected '{{'", line_no),
+                (_, Some(sup)) if sup.name.is_empty() => sup.name = line.to_string(),
+                (_, Some(sup)) if sup.kind.is_empty() => {
+                    if line != "*" && !BugType::NAMES.contains(&line) {
+                        bail!("line {}: unknown bug type '{}'", line_no, line);
+                    }
+                    sup.kind = line.to_string();
+                }
+                ("...", Some(sup)) => sup.frames.push(FramePattern::Any),
+                (_, Some(sup)) => {
+                    let pat = match line.split_once(':') {
+                        Some(("fun", g)) => FramePattern::Fun(glob_to_regex(g)?),
+                        Some(("mod", g)) => FramePattern::Module(glob_to_regex(g)?),
+                        Some(("file", g)) => FramePattern::File(glob_to_regex(g)?),
+                        _ => bail!("line {}: bad frame pattern '{}'", line_no, line),
+                    };
+                    sup.frames.push(pat);
+                }
+            }
+        }
+        if current.is_some() {
+            bail!("missing '}}' at end of file");
+        }
+        Ok(sups)
+    }
+
+    fn matches(&self, kind: &str, frames: &[FrameInfo]) -> bool {
+        (self.kind == "*" || self.kind == kind) && Self::match_frames(&self.frames, frames)
+    }
+    fn match_frames(pats: &[FramePattern], frames: &[FrameInfo]) -> bool {
+        let Some(pat) = pats.first() else {
+            return true;
+        };
+        if let FramePattern::Any = pat {
+            // try every possible number of skipped frames
+            return (0..=frames.len()).any(|i| Self::match_frames(&pats[1..], &frames[i..]));
+        }
+        let Some(frame) = frames.first() else {
+            return false;
+        };
+        let hit = match pat {
+            FramePattern::Fun(re) => re.is_match(&frame.fun),
+            FramePattern::Module(re) => re.is_match(&frame.module),
+            FramePattern::File(re) => re.is_match(&frame.file),
+            FramePattern::Any => unreachable!(),
+        };
+        hit && Self::match_frames(&pats[1..], &frames[1..])
+    }
+}
+
+impl Debugger {
+    pub fn load_suppressions(&mut self, path: &Path) -> Result<usize> {
+        let mut text = String::new();
+        File::open(path)?.read_to_string(&mut text)?;
+        let sups =
+            Suppression::parse(&text).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+        let count = sups.len();
+        self.suppressions.extend(sups);
+        Ok(count)
+    }
+    pub fn clear_suppressions(&mut self) {
+        self.suppressions.clear();
+    }
+    // is this bug at pc (with the current call stack) suppressed?
+    pub(crate) fn is_suppressed(&mut self, pc: u16, bug: &BugType) -> Result<bool> {
+        if self.suppressions.is_empty() {
+            return Ok(false);
+        }
+        let mut frames = vec![self.frame_info(pc)?];
+        for addr in self.bug_stack(pc) {
+            frames.push(self.frame_info(addr)?);
+        }
+        let kind = bug.name();
+        if let Some(sup) = self
+            .suppressions
+            .iter_mut()
+            .find(|s| s.matches(kind, &frames))
+        {
+            sup.hits += 1;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+    fn frame_info(&self, addr: u16) -> Result<FrameInfo> {
+        let mut info = FrameInfo::default();
+        if let Some((fun, _)) = self.dbgdb.find_function_by_addr(addr)? {
+            info.fun = fun;
+        }
+        let waw = self.where_are_we(addr)?;
+        if let Some(module) = waw.module {
+            if let Some(chunk) = self
+                .seg_list
+                .iter()
+                .flat_map(|s| s.modules.iter())
+                .find(|m| m.module == module)
+            {
+                info.module = chunk.module_name.clone();
+            }
+        }
+        if let Some(file_id) = waw.cfile.or(waw.afile) {
+            if let Some(file) = self.lookup_file_by_id(file_id) {
+                info.file = file.short_name.clone();
+            }
+        }
+        Ok(info)
+    }
+}
+
+#[test]
+fn suppress_frames() {
+    let sups = Suppression::parse(
+        "# a comment\n\
+         {\n\
+            ldaxi in main\n\
+            Memcheck\n\
+            fun:ldaxi*\n\
+            ...\n\
+            file:*.c\n\
+         }\n",
+    )
+    .unwrap();
+    assert_eq!(sups.len(), 1);
+    let sup = &sups[0];
+    assert_eq!(
+        (sup.name.as_str(), sup.kind.as_str()),
+        ("ldaxi in main", "Memcheck")
+    );
+    let stack = |funs: &[&str]| {
+        funs.iter()
+            .map(|f| FrameInfo {
+                fun: f.to_string(),
+                file: format!("{}.{}", f, if f.starts_with('_') { "c" } else { "s" }),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>()
+    };
+    // '...' matches no frames or any number of them
+    assert!(sup.matches("Memcheck", &stack(&["ldaxidx", "_main"])));
+    assert!(sup.matches("Memcheck", &stack(&["ldaxidx", "pushax", "ldai", "_main"])));
+    assert!(!sup.matches("HeapCheck", &stack(&["ldaxidx", "_main"])));
+    assert!(!sup.matches("Memcheck", &stack(&["ldaidx", "_main"])));
+    assert!(!sup.matches("Memcheck", &stack(&["ldaxidx", "pushax"])));
+
+    // without it every frame has to match in turn
+    let sups = Suppression::parse("{\nmod\n*\nfun:_peek\nmod:lib?.o\n}\n").unwrap();
+    let frames = [
+        FrameInfo {
+            fun: "_peek".to_string(),
+            ..Default::default()
+        },
+        FrameInfo {
+            module: "lib1.o".to_string(),
+            ..Default::default()
+        },
+    ];
+    assert!(sups[0].matches("SegCheck", &frames));
+    assert!(!sups[0].matches("SegCheck", &frames[..1]));
+    assert!(!sups[0].matches("SegCheck", &frames[1..]));
+}
+#[test]
+fn suppress_globs() {
+    let re = glob_to_regex("a.b+(c)[d]$?*").unwrap();
+    assert!(re.is_match("a.b+(c)[d]$x"));
+    assert!(re.is_match("a.b+(c)[d]$xyz"));
+    assert!(!re.is_match("aXb+(c)[d]$x"));
+    assert!(!re.is_match("a.bb(c)[d]$x"));
+    assert!(!re.is_match("a.b+(c)[d]$"));
+    assert!(!re.is_match("xa.b+(c)[d]$x"));
+
+    assert!(Suppression::parse("{\nname\nNoSuchBug\n}\n").is_err());
+    assert!(Suppression::parse("{\nname\n*\nsym:foo\n}\n").is_err());
+    assert!(Suppression::parse("{\nname\n*\nfun:foo\n").is_err());
+    assert!(Suppression::parse("fun:foo\n").is_err());
+}
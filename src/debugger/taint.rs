@@ -0,0 +1,583 @@
+/*
+Tracking of undefined values, like valgrind's memcheck does it
+
+A byte of memory is undefined until something writes it (no WRITTEN
+shadow flag). That only goes for memory the program may read (heap,
+stack, bss), reading anywhere else is reported straight away. Copying an undefined value around is fine, struct copies
+and memcpy do it all the time. Only using one is a bug
+
+- a conditional branch on flags computed from it
+- an address computed from it (index register, pointer, return address)
+- an argument to a paravirt call
+
+So instead of reporting every read of unwritten memory we follow where
+the undefined values go. Registers and flags remember the address the
+value came from (its origin), memory gets the TAINTED shadow flag and
+an entry in the origin table. Tracking is done a byte at a time, from
+the instruction and the registers just before it is executed
+*/
+use super::{
+    core::Debugger,
+    cpu::{Cpu, ShadowFlags},
+    execute::{BugType, UninitUse},
+    paravirt::{ParaVirt, PvArgs},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Adc,
+    And,
+    Asl,
+    Bcc,
+    Bcs,
+    Beq,
+    Bit,
+    Bmi,
+    Bne,
+    Bpl,
+    Bra,
+    Brk,
+    Bvc,
+    Bvs,
+    Clc,
+    Cld,
+    Cli,
+    Clv,
+    Cmp,
+    Cpx,
+    Cpy,
+    Dec,
+    Dex,
+    Dey,
+    Eor,
+    Inc,
+    Inx,
+    Iny,
+    Jmp,
+    Jsr,
+    Lda,
+    Ldx,
+    Ldy,
+    Lsr,
+    Nop,
+    Ora,
+    Pha,
+    Php,
+    Phx,
+    Phy,
+    Pla,
+    Plp,
+    Plx,
+    Ply,
+    Rol,
+    Ror,
+    Rti,
+    Rts,
+    Sbc,
+    Sec,
+    Sed,
+    Sei,
+    Sta,
+    Stx,
+    Sty,
+    Stz,
+    Tax,
+    Tay,
+    Trb,
+    Tsb,
+    Tsx,
+    Txa,
+    Txs,
+    Tya,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Imp,
+    Acc,
+    Imm,
+    Zp,
+    ZpX,
+    ZpY,
+    Abs,
+    AbsX,
+    AbsY,
+    IndX,
+    IndY,
+    IndZp, // 65c02 (zp)
+    Ind,
+    AbsIndX, // 65c02 jmp (abs,x)
+    Rel,
+}
+
+// None if defined, otherwise the address the undefined value was read from
+type Taint = Option<u16>;
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RegTaint {
+    a: Taint,
+    x: Taint,
+    y: Taint,
+    c: Taint,
+    nz: Taint,
+    v: Taint,
+}
+
+// 6502 and 65c02 documented opcodes
+fn decode(opcode: u8) -> Option<(Op, Mode)> {
+    match opcode {
+        0x00 => Some((Op::Brk, Mode::Imp)),
+        0x01 => Some((Op::Ora, Mode::IndX)),
+        0x04 => Some((Op::Tsb, Mode::Zp)),
+        0x05 => Some((Op::Ora, Mode::Zp)),
+        0x06 => Some((Op::Asl, Mode::Zp)),
+        0x08 => Some((Op::Php, Mode::Imp)),
+        0x09 => Some((Op::Ora, Mode::Imm)),
+        0x0a => Some((Op::Asl, Mode::Acc)),
+        0x0c => Some((Op::Tsb, Mode::Abs)),
+        0x0d => Some((Op::Ora, Mode::Abs)),
+        0x0e => Some((Op::Asl, Mode::Abs)),
+        0x10 => Some((Op::Bpl, Mode::Rel)),
+        0x11 => Some((Op::Ora, Mode::IndY)),
+        0x12 => Some((Op::Ora, Mode::IndZp)),
+        0x14 => Some((Op::Trb, Mode::Zp)),
+        0x15 => Some((Op::Ora, Mode::ZpX)),
+        0x16 => Some((Op::Asl, Mode::ZpX)),
+        0x18 => Some((Op::Clc, Mode::Imp)),
+        0x19 => Some((Op::Ora, Mode::AbsY)),
+        0x1a => Some((Op::Inc, Mode::Acc)),
+        0x1c => Some((Op::Trb, Mode::Abs)),
+        0x1d => Some((Op::Ora, Mode::AbsX)),
+        0x1e => Some((Op::Asl, Mode::AbsX)),
+        0x20 => Some((Op::Jsr, Mode::Abs)),
+        0x21 => Some((Op::And, Mode::IndX)),
+        0x24 => Some((Op::Bit, Mode::Zp)),
+        0x25 => Some((Op::And, Mode::Zp)),
+        0x26 => Some((Op::Rol, Mode::Zp)),
+        0x28 => Some((Op::Plp, Mode::Imp)),
+        0x29 => Some((Op::And, Mode::Imm)),
+        0x2a => Some((Op::Rol, Mode::Acc)),
+        0x2c => Some((Op::Bit, Mode::Abs)),
+        0x2d => Some((Op::And, Mode::Abs)),
+        0x2e => Some((Op::Rol, Mode::Abs)),
+        0x30 => Some((Op::Bmi, Mode::Rel)),
+        0x31 => Some((Op::And, Mode::IndY)),
+        0x32 => Some((Op::And, Mode::IndZp)),
+        0x34 => Some((Op::Bit, Mode::ZpX)),
+        0x35 => Some((Op::And, Mode::ZpX)),
+        0x36 => Some((Op::Rol, Mode::ZpX)),
+        0x38 => Some((Op::Sec, Mode::Imp)),
+        0x39 => Some((Op::And, Mode::AbsY)),
+        0x3a => Some((Op::Dec, Mode::Acc)),
+        0x3c => Some((Op::Bit, Mode::AbsX)),
+        0x3d => Some((Op::And, Mode::AbsX)),
+        0x3e => Some((Op::Rol, Mode::AbsX)),
+        0x40 => Some((Op::Rti, Mode::Imp)),
+        0x41 => Some((Op::Eor, Mode::IndX)),
+        0x45 => Some((Op::Eor, Mode::Zp)),
+        0x46 => Some((Op::Lsr, Mode::Zp)),
+        0x48 => Some((Op::Pha, Mode::Imp)),
+        0x49 => Some((Op::Eor, Mode::Imm)),
+        0x4a => Some((Op::Lsr, Mode::Acc)),
+        0x4c => Some((Op::Jmp, Mode::Abs)),
+        0x4d => Some((Op::Eor, Mode::Abs)),
+        0x4e => Some((Op::Lsr, Mode::Abs)),
+        0x50 => Some((Op::Bvc, Mode::Rel)),
+        0x51 => Some((Op::Eor, Mode::IndY)),
+        0x52 => Some((Op::Eor, Mode::IndZp)),
+        0x55 => Some((Op::Eor, Mode::ZpX)),
+        0x56 => Some((Op::Lsr, Mode::ZpX)),
+        0x58 => Some((Op::Cli, Mode::Imp)),
+        0x59 => Some((Op::Eor, Mode::AbsY)),
+        0x5a => Some((Op::Phy, Mode::Imp)),
+        0x5d => Some((Op::Eor, Mode::AbsX)),
+        0x5e => Some((Op::Lsr, Mode::AbsX)),
+        0x60 => Some((Op::Rts, Mode::Imp)),
+        0x61 => Some((Op::Adc, Mode::IndX)),
+        0x64 => Some((Op::Stz, Mode::Zp)),
+        0x65 => Some((Op::Adc, Mode::Zp)),
+        0x66 => Some((Op::Ror, Mode::Zp)),
+        0x68 => Some((Op::Pla, Mode::Imp)),
+        0x69 => Some((Op::Adc, Mode::Imm)),
+        0x6a => Some((Op::Ror, Mode::Acc)),
+        0x6c => Some((Op::Jmp, Mode::Ind)),
+        0x6d => Some((Op::Adc, Mode::Abs)),
+        0x6e => Some((Op::Ror, Mode::Abs)),
+        0x70 => Some((Op::Bvs, Mode::Rel)),
+        0x71 => Some((Op::Adc, Mode::IndY)),
+        0x72 => Some((Op::Adc, Mode::IndZp)),
+        0x74 => Some((Op::Stz, Mode::ZpX)),
+        0x75 => Some((Op::Adc, Mode::ZpX)),
+        0x76 => Some((Op::Ror, Mode::ZpX)),
+        0x78 => Some((Op::Sei, Mode::Imp)),
+        0x79 => Some((Op::Adc, Mode::AbsY)),
+        0x7a => Some((Op::Ply, Mode::Imp)),
+        0x7c => Some((Op::Jmp, Mode::AbsIndX)),
+        0x7d => Some((Op::Adc, Mode::AbsX)),
+        0x7e => Some((Op::Ror, Mode::AbsX)),
+        0x80 => Some((Op::Bra, Mode::Rel)),
+        0x81 => Some((Op::Sta, Mode::IndX)),
+        0x84 => Some((Op::Sty, Mode::Zp)),
+        0x85 => Some((Op::Sta, Mode::Zp)),
+        0x86 => Some((Op::Stx, Mode::Zp)),
+        0x88 => Some((Op::Dey, Mode::Imp)),
+        0x89 => Some((Op::Bit, Mode::Imm)),
+        0x8a => Some((Op::Txa, Mode::Imp)),
+        0x8c => Some((Op::Sty, Mode::Abs)),
+        0x8d => Some((Op::Sta, Mode::Abs)),
+        0x8e => Some((Op::Stx, Mode::Abs)),
+        0x90 => Some((Op::Bcc, Mode::Rel)),
+        0x91 => Some((Op::Sta, Mode::IndY)),
+        0x92 => Some((Op::Sta, Mode::IndZp)),
+        0x94 => Some((Op::Sty, Mode::ZpX)),
+        0x95 => Some((Op::Sta, Mode::ZpX)),
+        0x96 => Some((Op::Stx, Mode::ZpY)),
+        0x98 => Some((Op::Tya, Mode::Imp)),
+        0x99 => Some((Op::Sta, Mode::AbsY)),
+        0x9a => Some((Op::Txs, Mode::Imp)),
+        0x9c => Some((Op::Stz, Mode::Abs)),
+        0x9d => Some((Op::Sta, Mode::AbsX)),
+        0x9e => Some((Op::Stz, Mode::AbsX)),
+        0xa0 => Some((Op::Ldy, Mode::Imm)),
+        0xa1 => Some((Op::Lda, Mode::IndX)),
+        0xa2 => Some((Op::Ldx, Mode::Imm)),
+        0xa4 => Some((Op::Ldy, Mode::Zp)),
+        0xa5 => Some((Op::Lda, Mode::Zp)),
+        0xa6 => Some((Op::Ldx, Mode::Zp)),
+        0xa8 => Some((Op::Tay, Mode::Imp)),
+        0xa9 => Some((Op::Lda, Mode::Imm)),
+        0xaa => Some((Op::Tax, Mode::Imp)),
+        0xac => Some((Op::Ldy, Mode::Abs)),
+        0xad => Some((Op::Lda, Mode::Abs)),
+        0xae => Some((Op::Ldx, Mode::Abs)),
+        0xb0 => Some((Op::Bcs, Mode::Rel)),
+        0xb1 => Some((Op::Lda, Mode::IndY)),
+        0xb2 => Some((Op::Lda, Mode::IndZp)),
+        0xb4 => Some((Op::Ldy, Mode::ZpX)),
+        0xb5 => Some((Op::Lda, Mode::ZpX)),
+        0xb6 => Some((Op::Ldx, Mode::ZpY)),
+        0xb8 => Some((Op::Clv, Mode::Imp)),
+        0xb9 => Some((Op::Lda, Mode::AbsY)),
+        0xba => Some((Op::Tsx, Mode::Imp)),
+        0xbc => Some((Op::Ldy, Mode::AbsX)),
+        0xbd => Some((Op::Lda, Mode::AbsX)),
+        0xbe => Some((Op::Ldx, Mode::AbsY)),
+        0xc0 => Some((Op::Cpy, Mode::Imm)),
+        0xc1 => Some((Op::Cmp, Mode::IndX)),
+        0xc4 => Some((Op::Cpy, Mode::Zp)),
+        0xc5 => Some((Op::Cmp, Mode::Zp)),
+        0xc6 => Some((Op::Dec, Mode::Zp)),
+        0xc8 => Some((Op::Iny, Mode::Imp)),
+        0xc9 => Some((Op::Cmp, Mode::Imm)),
+        0xca => Some((Op::Dex, Mode::Imp)),
+        0xcc => Some((Op::Cpy, Mode::Abs)),
+        0xcd => Some((Op::Cmp, Mode::Abs)),
+        0xce => Some((Op::Dec, Mode::Abs)),
+        0xd0 => Some((Op::Bne, Mode::Rel)),
+        0xd1 => Some((Op::Cmp, Mode::IndY)),
+        0xd2 => Some((Op::Cmp, Mode::IndZp)),
+        0xd5 => Some((Op::Cmp, Mode::ZpX)),
+        0xd6 => Some((Op::Dec, Mode::ZpX)),
+        0xd8 => Some((Op::Cld, Mode::Imp)),
+        0xd9 => Some((Op::Cmp, Mode::AbsY)),
+        0xda => Some((Op::Phx, Mode::Imp)),
+        0xdd => Some((Op::Cmp, Mode::AbsX)),
+        0xde => Some((Op::Dec, Mode::AbsX)),
+        0xe0 => Some((Op::Cpx, Mode::Imm)),
+        0xe1 => Some((Op::Sbc, Mode::IndX)),
+        0xe4 => Some((Op::Cpx, Mode::Zp)),
+        0xe5 => Some((Op::Sbc, Mode::Zp)),
+        0xe6 => Some((Op::Inc, Mode::Zp)),
+        0xe8 => Some((Op::Inx, Mode::Imp)),
+        0xe9 => Some((Op::Sbc, Mode::Imm)),
+        0xea => Some((Op::Nop, Mode::Imp)),
+        0xec => Some((Op::Cpx, Mode::Abs)),
+        0xed => Some((Op::Sbc, Mode::Abs)),
+        0xee => Some((Op::Inc, Mode::Abs)),
+        0xf0 => Some((Op::Beq, Mode::Rel)),
+        0xf1 => Some((Op::Sbc, Mode::IndY)),
+        0xf2 => Some((Op::Sbc, Mode::IndZp)),
+        0xf5 => Some((Op::Sbc, Mode::ZpX)),
+        0xf6 => Some((Op::Inc, Mode::ZpX)),
+        0xf8 => Some((Op::Sed, Mode::Imp)),
+        0xf9 => Some((Op::Sbc, Mode::AbsY)),
+        0xfa => Some((Op::Plx, Mode::Imp)),
+        0xfd => Some((Op::Sbc, Mode::AbsX)),
+        0xfe => Some((Op::Inc, Mode::AbsX)),
+        _ => None,
+    }
+}
+
+// instruction length in bytes, unknown opcodes count as one
+pub(crate) fn insn_len(opcode: u8) -> u16 {
+    match decode(opcode) {
+        Some((_, Mode::Imp | Mode::Acc)) | None => 1,
+        Some((_, Mode::Abs | Mode::AbsX | Mode::AbsY | Mode::Ind | Mode::AbsIndX)) => 3,
+        Some(_) => 2,
+    }
+}
+
+// the pointer an indirect instruction goes through, where it is and its
+// value (without y added). Used by the NULL pointer check
+pub(crate) fn indirect_pointer(pc: u16) -> Option<(u16, u16)> {
+    let (_, mode) = decode(Cpu::read_byte(pc))?;
+    let b1 = Cpu::read_byte(pc.wrapping_add(1));
+    let w1 = Cpu::read_word(pc.wrapping_add(1));
+    let zp_ptr = |zp: u8| {
+        let value = Cpu::read_byte(zp as u16) as u16
+            | (Cpu::read_byte(zp.wrapping_add(1) as u16) as u16) << 8;
+        Some((zp as u16, value))
+    };
+    match mode {
+        Mode::IndX => zp_ptr(b1.wrapping_add(Cpu::read_xr())),
+        Mode::IndY | Mode::IndZp => zp_ptr(b1),
+        Mode::Ind => Some((w1, Cpu::read_word(w1))),
+        Mode::AbsIndX => {
+            let ptr = w1.wrapping_add(Cpu::read_xr() as u16);
+            Some((ptr, Cpu::read_word(ptr)))
+        }
+        _ => None,
+    }
+}
+
+impl Debugger {
+    // is the byte at addr undefined, and if so where did it come from
+    pub(crate) fn mem_taint(&self, addr: u16) -> Taint {
+        let flags = Cpu::get_shadow()[addr as usize];
+        if !flags.contains(ShadowFlags::WRITTEN) && flags.contains(ShadowFlags::READ) {
+            Some(addr)
+        } else if flags.contains(ShadowFlags::TAINTED) {
+            Some(self.taint_origin[addr as usize])
+        } else {
+            None
+        }
+    }
+    fn set_mem_taint(&mut self, addr: u16, taint: Taint) {
+        let shadow = Cpu::get_shadow();
+        match taint {
+            Some(origin) => {
+                shadow[addr as usize] |= ShadowFlags::TAINTED;
+                self.taint_origin[addr as usize] = origin;
+            }
+            None => shadow[addr as usize].remove(ShadowFlags::TAINTED),
+        }
+    }
+
+    // follow the undefined values through the instruction at pc, which
+    // is about to be executed. Returns the bug if it uses one
+    pub(crate) fn track_taint(&mut self, pc: u16) -> Option<BugType> {
+        let (op, mode) = decode(Cpu::read_byte(pc))?;
+        let b1 = Cpu::read_byte(pc.wrapping_add(1));
+        let w1 = Cpu::read_word(pc.wrapping_add(1));
+        let (x, y, sp) = (Cpu::read_xr(), Cpu::read_yr(), Cpu::read_sp());
+        let mut r = self.reg_taint;
+
+        let zp_ptr = |zp: u8| {
+            Cpu::read_byte(zp as u16) as u16
+                | (Cpu::read_byte(zp.wrapping_add(1) as u16) as u16) << 8
+        };
+        let zp_ptr_taint = |zp: u8| {
+            self.mem_taint(zp as u16)
+                .or(self.mem_taint(zp.wrapping_add(1) as u16))
+        };
+        let ptr_taint = |addr: u16| {
+            self.mem_taint(addr)
+                .or(self.mem_taint(addr.wrapping_add(1)))
+        };
+
+        // the effective address, and whatever went into calculating it
+        let (ea, addr_taint) = match mode {
+            Mode::Zp => (b1 as u16, None),
+            Mode::ZpX => (b1.wrapping_add(x) as u16, r.x),
+            Mode::ZpY => (b1.wrapping_add(y) as u16, r.y),
+            Mode::Abs => (w1, None),
+            Mode::AbsX => (w1.wrapping_add(x as u16), r.x),
+            Mode::AbsY => (w1.wrapping_add(y as u16), r.y),
+            Mode::IndX => {
+                let zp = b1.wrapping_add(x);
+                (zp_ptr(zp), r.x.or(zp_ptr_taint(zp)))
+            }
+            Mode::IndY => (zp_ptr(b1).wrapping_add(y as u16), zp_ptr_taint(b1).or(r.y)),
+            Mode::IndZp => (zp_ptr(b1), zp_ptr_taint(b1)),
+            Mode::Ind => (Cpu::read_word(w1), ptr_taint(w1)),
+            Mode::AbsIndX => {
+                let ptr = w1.wrapping_add(x as u16);
+                (Cpu::read_word(ptr), r.x.or(ptr_taint(ptr)))
+            }
+            Mode::Imp | Mode::Acc | Mode::Imm | Mode::Rel => (0, None),
+        };
+        let mut bug = addr_taint.map(|o| BugType::UninitValue(UninitUse::Address, o));
+        let operand = match mode {
+            Mode::Imp | Mode::Imm | Mode::Rel => None,
+            Mode::Acc => r.a,
+            _ => self.mem_taint(ea),
+        };
+        let push = 0x100 | sp as u16;
+        let pull = 0x100 | sp.wrapping_add(1) as u16;
+        let branch = |flag: Taint| flag.map(|o| BugType::UninitValue(UninitUse::Branch, o));
+
+        match op {
+            Op::Lda => (r.a, r.nz) = (operand, operand),
+            Op::Ldx => (r.x, r.nz) = (operand, operand),
+            Op::Ldy => (r.y, r.nz) = (operand, operand),
+            Op::Sta => self.set_mem_taint(ea, r.a),
+            Op::Stx => self.set_mem_taint(ea, r.x),
+            Op::Sty => self.set_mem_taint(ea, r.y),
+            Op::Stz => self.set_mem_taint(ea, None),
+            Op::Adc | Op::Sbc => {
+                r.a = r.a.or(operand).or(r.c);
+                (r.nz, r.c, r.v) = (r.a, r.a, r.a);
+            }
+            Op::And | Op::Ora | Op::Eor => {
+                r.a = r.a.or(operand);
+                r.nz = r.a;
+            }
+            Op::Cmp => (r.c, r.nz) = (r.a.or(operand), r.a.or(operand)),
+            Op::Cpx => (r.c, r.nz) = (r.x.or(operand), r.x.or(operand)),
+            Op::Cpy => (r.c, r.nz) = (r.y.or(operand), r.y.or(operand)),
+            Op::Bit => {
+                r.nz = r.a.or(operand);
+                if mode != Mode::Imm {
+                    r.v = operand;
+                }
+            }
+            Op::Asl | Op::Lsr => {
+                (r.c, r.nz) = (operand, operand);
+                if mode != Mode::Acc {
+                    self.set_mem_taint(ea, operand);
+                }
+            }
+            Op::Rol | Op::Ror => {
+                let result = operand.or(r.c);
+                (r.c, r.nz) = (operand, result);
+                if mode == Mode::Acc {
+                    r.a = result;
+                } else {
+                    self.set_mem_taint(ea, result);
+                }
+            }
+            Op::Inc | Op::Dec => {
+                r.nz = operand;
+                if mode != Mode::Acc {
+                    self.set_mem_taint(ea, operand);
+                }
+            }
+            Op::Inx | Op::Dex => r.nz = r.x,
+            Op::Iny | Op::Dey => r.nz = r.y,
+            Op::Tax => (r.x, r.nz) = (r.a, r.a),
+            Op::Tay => (r.y, r.nz) = (r.a, r.a),
+            Op::Txa => (r.a, r.nz) = (r.x, r.x),
+            Op::Tya => (r.a, r.nz) = (r.y, r.y),
+            Op::Tsx => (r.x, r.nz) = (None, None),
+            Op::Txs => bug = bug.or(r.x.map(|o| BugType::UninitValue(UninitUse::Address, o))),
+            Op::Pha => self.set_mem_taint(push, r.a),
+            Op::Phx => self.set_mem_taint(push, r.x),
+            Op::Phy => self.set_mem_taint(push, r.y),
+            Op::Php => self.set_mem_taint(push, r.c.or(r.nz).or(r.v)),
+            Op::Pla => (r.a, r.nz) = (self.mem_taint(pull), self.mem_taint(pull)),
+            Op::Plx => (r.x, r.nz) = (self.mem_taint(pull), self.mem_taint(pull)),
+            Op::Ply => (r.y, r.nz) = (self.mem_taint(pull), self.mem_taint(pull)),
+            Op::Plp => {
+                let t = self.mem_taint(pull);
+                (r.c, r.nz, r.v) = (t, t, t);
+            }
+            Op::Clc | Op::Sec => r.c = None,
+            Op::Clv => r.v = None,
+            Op::Bcc | Op::Bcs => bug = bug.or(branch(r.c)),
+            Op::Beq | Op::Bne | Op::Bmi | Op::Bpl => bug = bug.or(branch(r.nz)),
+            Op::Bvc | Op::Bvs => bug = bug.or(branch(r.v)),
+            Op::Trb | Op::Tsb => {
+                r.nz = operand.or(r.a);
+                self.set_mem_taint(ea, r.nz);
+            }
+            Op::Jsr => {
+                // the return address is always good
+                self.set_mem_taint(push, None);
+                self.set_mem_taint(0x100 | sp.wrapping_sub(1) as u16, None);
+            }
+            Op::Rts => {
+                let ret = self
+                    .mem_taint(pull)
+                    .or(self.mem_taint(0x100 | sp.wrapping_add(2) as u16));
+                bug = bug.or(ret.map(|o| BugType::UninitValue(UninitUse::Address, o)));
+            }
+            Op::Jmp | Op::Bra | Op::Brk | Op::Rti | Op::Nop => {}
+            Op::Cld | Op::Cli | Op::Sed | Op::Sei => {}
+        }
+
+        // paravirt calls look at their args and set AX
+        if matches!(op, Op::Jsr | Op::Jmp) {
+            if let Some(target) = ParaVirt::pv_target(pc) {
+                let used = self.pv_arg_taint(target, &r);
+                bug = bug.or(used.map(|o| BugType::UninitValue(UninitUse::SysCall, o)));
+                (r.a, r.x) = (None, None);
+            }
+        }
+        self.reg_taint = r;
+        bug
+    }
+    fn pv_arg_taint(&self, target: u16, r: &RegTaint) -> Taint {
+        let sp65 = Cpu::read_word(Cpu::get_sp65_addr() as u16);
+        let arg = |offset: u16| {
+            let addr = sp65.wrapping_add(offset);
+            self.mem_taint(addr)
+                .or(self.mem_taint(addr.wrapping_add(1)))
+        };
+        match ParaVirt::peek_args(target) {
+            PvArgs::Exit { .. } => r.a,
+            PvArgs::Open { .. } | PvArgs::Close { .. } | PvArgs::Args { .. } => r.a.or(r.x),
+            PvArgs::Read { .. } => r.a.or(r.x).or(arg(0)).or(arg(2)),
+            PvArgs::Write { buf, count, .. } => r.a.or(r.x).or(arg(0)).or(arg(2)).or_else(|| {
+                // what gets written out must be defined too
+                (0..count).find_map(|i| self.mem_taint(buf.wrapping_add(i)))
+            }),
+        }
+    }
+}
+#[test]
+fn taint_copy() {
+    use super::execute::StopReason;
+    let _cpu = Cpu::test_lock();
+    let mut dbg = Debugger::new();
+    dbg.enable_mem_check = true;
+    let shadow = Cpu::get_shadow();
+    shadow[0x6000] = ShadowFlags::READ | ShadowFlags::WRITE;
+    shadow[0x6100] = ShadowFlags::READ | ShadowFlags::WRITE;
+    // lda $6000, sta $6100 is only a copy
+    Cpu::test_code(0x0200, &[0xad, 0x00, 0x60, 0x8d, 0x00, 0x61]);
+    assert!(matches!(dbg.execute(2).unwrap(), StopReason::Count));
+    assert_eq!(dbg.mem_taint(0x6100), Some(0x6000));
+
+    // inc, asl, lsr and dec of an undefined byte leave it undefined
+    for op in [0xee, 0x0e, 0x4e, 0xce] {
+        shadow[0x6000] = ShadowFlags::READ | ShadowFlags::WRITE;
+        Cpu::test_code(0x0200, &[op, 0x00, 0x60]);
+        assert!(matches!(dbg.execute(1).unwrap(), StopReason::Count));
+        assert_eq!(dbg.mem_taint(0x6000), Some(0x6000), "opcode {:02x}", op);
+    }
+}
+#[test]
+fn taint_use() {
+    use super::execute::StopReason;
+    let _cpu = Cpu::test_lock();
+    let mut dbg = Debugger::new();
+    dbg.enable_mem_check = true;
+    let shadow = Cpu::get_shadow();
+    shadow[0x6000] = ShadowFlags::READ | ShadowFlags::WRITE;
+    // lda $6000, beq
+    Cpu::test_code(0x0200, &[0xad, 0x00, 0x60, 0xf0, 0x00]);
+    assert!(matches!(
+        dbg.execute(2).unwrap(),
+        StopReason::Bug(BugType::UninitValue(UninitUse::Branch, 0x6000))
+    ));
+
+    // lda ($80),y with the low byte of the pointer undefined
+    Cpu::write_word(0x80, 0x6000);
+    shadow[0x80] = ShadowFlags::READ | ShadowFlags::WRITE;
+    shadow[0x81] |= ShadowFlags::READ;
+    Cpu::write_yr(0);
+    Cpu::test_code(0x0200, &[0xb1, 0x80]);
+    assert!(matches!(
+        dbg.execute(1).unwrap(),
+        StopReason::Bug(BugType::UninitValue(UninitUse::Address, 0x80))
+    ));
+}
@@ -778,6 +778,7 @@ impl Debugger {
 }
 #[test]
 fn test_dis() {
+    let _cpu = crate::debugger::cpu::Cpu::test_lock();
     let mut dbg = Debugger::new();
     let mem = vec![0x00, 0x01, 0x02];
     let len = dbg.dis(&mem, 0);
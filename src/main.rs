@@ -14,13 +14,18 @@ use util::log::init_log;
 //     pub mod util;
 // }
 mod debugger {
+    pub mod abi;
     pub mod core;
     pub mod cpu;
     pub mod execute;
+    pub mod heap;
     pub mod intercepts;
+    pub mod lint;
     pub mod loader;
     pub mod paravirt;
     pub mod plumbing;
+    pub mod suppress;
+    pub mod taint;
 }
 mod dis;
 
@@ -43,6 +48,9 @@ struct Cli {
 
     #[arg(short, long)]
     set_exit: bool,
+
+    #[arg(long, value_name = "FILE")]
+    suppressions: Vec<PathBuf>,
     #[arg(last = true)]
     args: Vec<String>,
 }
@@ -58,6 +66,6 @@ fn main() -> Result<()> {
     );
     println!("use 'help' to get help for commands and 'about' for more information");
     let mut sh = Shell::new();
-    sh.shell(cli.command_file, &cli.args)?;
+    sh.shell(cli.command_file, &cli.suppressions, &cli.args)?;
     Ok(())
 }
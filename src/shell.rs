@@ -1,8 +1,9 @@
 #![allow(clippy::uninlined_format_args)]
 use crate::about::About;
-use crate::debugger::core::{CodeLocation, Debugger, FrameType::*, WatchType};
+use crate::debugger::core::{CatchWhen, CodeLocation, Debugger, FrameType::*, WatchType};
 use crate::debugger::cpu::Status;
-use crate::debugger::execute::{BugType, StopReason};
+use crate::debugger::execute::{BugType, StopReason, TrapMode};
+use crate::debugger::heap::{FailPolicy, HeapOp, LeakKind, HEAP_HISTORY};
 use dbgdata::debugdb::SymbolType;
 
 use crate::syntax;
@@ -68,9 +69,19 @@ impl Shell {
             println!("{s}")
         };
     }
-    pub fn shell(&mut self, file: Option<PathBuf>, _args: &[String]) -> Result<u8> {
+    pub fn shell(
+        &mut self,
+        file: Option<PathBuf>,
+        suppressions: &[PathBuf],
+        _args: &[String],
+    ) -> Result<u8> {
         let mut rl = DefaultEditor::new()?;
         util::say::set_say_cb(Self::say);
+        for sup in suppressions {
+            if let Err(e) = self.debugger.load_suppressions(sup) {
+                println!("{}", e);
+            }
+        }
         if let Err(e) = rl.load_history(SHELL_HISTORY_FILE) {
             if let ReadlineError::Io(ref re) = e {
                 if re.kind() != std::io::ErrorKind::NotFound {
@@ -178,6 +189,15 @@ impl Shell {
                     self.debugger.set_break(addr, false)?;
                 }
             }
+            Some(("rbreak", args)) => {
+                let pattern = args.get_one::<String>("regex").unwrap();
+                let module = args.get_one::<String>("module");
+                let names = self.debugger.set_regex_break(pattern, module)?;
+                for name in &names {
+                    println!("  {}", name);
+                }
+                println!("{} break points in group '{}'", names.len(), pattern);
+            }
             Some(("watch", args)) => {
                 let addr = args.get_one::<String>("address").unwrap();
                 let addr = &self.expand_expr(addr)?;
@@ -194,11 +214,26 @@ impl Shell {
                 };
                 self.debugger.set_watch(addr, rw)?;
             }
+            Some(("catch", args)) => {
+                let call = args.get_one::<String>("call").unwrap();
+                let before = *args.get_one::<bool>("before").unwrap();
+                let after = *args.get_one::<bool>("after").unwrap();
+                let when = match (before, after) {
+                    (true, false) => CatchWhen::Before,
+                    (false, true) => CatchWhen::After,
+                    _ => CatchWhen::Both,
+                };
+                self.debugger.set_catch(call, when)?;
+            }
             Some(("list_breakpoints", _)) => {
                 let blist = self.debugger.get_breaks()?;
 
                 for (i, bp) in blist.values().enumerate() {
-                    println!("#{} 0x{:04X} ({})", i + 1, bp.addr, bp.symbol);
+                    if let Some(group) = &bp.group {
+                        println!("#{} 0x{:04X} ({}) [{}]", i + 1, bp.addr, bp.symbol, group);
+                    } else {
+                        println!("#{} 0x{:04X} ({})", i + 1, bp.addr, bp.symbol);
+                    }
                 }
             }
             Some(("list_watchpoints", _)) => {
@@ -214,6 +249,13 @@ impl Shell {
                     );
                 }
             }
+            Some(("list_catchpoints", _)) => {
+                let clist = self.debugger.get_catches()?;
+
+                for (i, cp) in clist.values().enumerate() {
+                    println!("#{} 0x{:04X} ({}) {:?}", i + 1, cp.addr, cp.name, cp.when);
+                }
+            }
             Some(("load_dbginfo", args)) => {
                 let file = args.get_one::<String>("file").unwrap();
                 self.debugger.load_dbg(Path::new(file))?;
@@ -288,13 +330,35 @@ impl Shell {
             }
 
             Some(("delete_breakpoint", args)) => {
-                let id = args.get_one::<String>("id");
-                self.debugger.delete_breakpoint(id)?;
+                if let Some(group) = args.get_one::<String>("group") {
+                    let count = self.debugger.delete_breakpoint_group(group)?;
+                    println!("deleted {} break points", count);
+                } else {
+                    let id = args.get_one::<String>("id");
+                    self.debugger.delete_breakpoint(id)?;
+                }
             }
             Some(("delete_watchpoint", args)) => {
                 let id = args.get_one::<String>("id");
                 self.debugger.delete_watchpoint(id)?;
             }
+            Some(("delete_catchpoint", args)) => {
+                let id = args.get_one::<String>("id");
+                self.debugger.delete_catchpoint(id)?;
+            }
+            Some(("suppress", args)) => {
+                if args.get_flag("clear") {
+                    self.debugger.clear_suppressions();
+                }
+                if let Some(file) = args.get_one::<String>("file") {
+                    let count = self.debugger.load_suppressions(Path::new(file))?;
+                    println!("loaded {} suppressions", count);
+                } else if !args.get_flag("clear") {
+                    for (i, sup) in self.debugger.get_suppressions().iter().enumerate() {
+                        println!("#{} {} ({}) used {}", i + 1, sup.name, sup.kind, sup.hits);
+                    }
+                }
+            }
             Some(("back_trace", _)) => {
                 let stack = self.debugger.read_stack();
                 print!("0x{:04x} ", self.waw.absaddr);
@@ -345,6 +409,11 @@ impl Shell {
                             pd.value,
                             self.debugger.symbol_lookup(pd.addr)?
                         ),
+                        Brk(bd) => println!(
+                            "brk @{} -> {}",
+                            self.debugger.symbol_lookup(bd.call_addr.wrapping_sub(2))?,
+                            self.debugger.symbol_lookup(bd.dest_addr)?
+                        ),
                     }
                 }
             }
@@ -373,7 +442,12 @@ impl Shell {
                     if !addr_str.starts_with('$') {
                         println!("{}:", addr_str);
                     }
-                    println!("{:04x}:       {}", addr, self.debugger.dis_line);
+                    let mark = if self.debugger.is_patched(addr, delta) {
+                        '*'
+                    } else {
+                        ' '
+                    };
+                    println!("{:04x}:     {} {}", addr, mark, self.debugger.dis_line);
                     addr += delta as u16;
                     self.current_dis_addr = addr;
                 }
@@ -530,20 +604,83 @@ impl Shell {
                     println!("{}", self.about.get_topic("topics"));
                 }
             }
-            Some(("display_heap", _args)) => {
+            Some(("display_heap", args)) => {
+                match args.subcommand() {
+                    Some(("check", _)) => {
+                        self.check_heap()?;
+                        return Ok(false);
+                    }
+                    Some(("break", args)) => {
+                        match args.get_one::<usize>("number") {
+                            Some(0) => self.debugger.set_heap_break(None),
+                            Some(num) => self.debugger.set_heap_break(Some(*num)),
+                            None => match self.debugger.get_heap_break() {
+                                Some(num) => println!("heap break at allocation #{}", num),
+                                None => println!("no heap break set"),
+                            },
+                        }
+                        return Ok(false);
+                    }
+                    Some(("fail", args)) => {
+                        let policy = if let Some(n) = args.get_one::<usize>("nth") {
+                            Some(FailPolicy::Nth(*n))
+                        } else if let Some(b) = args.get_one::<usize>("after_bytes") {
+                            Some(FailPolicy::AfterBytes(*b))
+                        } else if let Some(p) = args.get_one::<f64>("probability") {
+                            if !(0.0..=1.0).contains(p) {
+                                bail!("probability must be between 0 and 1");
+                            }
+                            let seed = args.get_one::<u64>("seed").copied().unwrap_or(1);
+                            Some(FailPolicy::Random {
+                                probability: *p,
+                                seed,
+                            })
+                        } else if args.get_flag("off") {
+                            None
+                        } else {
+                            match self.debugger.get_heap_fail() {
+                                Some(policy) => println!("heap fail: {}", policy),
+                                None => println!("heap fail: off"),
+                            }
+                            return Ok(false);
+                        };
+                        self.debugger.set_heap_fail(policy);
+                        return Ok(false);
+                    }
+                    Some(("stats", _)) => {
+                        self.heap_stats()?;
+                        return Ok(false);
+                    }
+                    Some(("history", args)) => {
+                        let addr = if let Some(addr_str) = args.get_one::<String>("address") {
+                            let addr_str = &self.expand_expr(addr_str)?;
+                            Some(self.debugger.convert_addr(addr_str)?.0)
+                        } else {
+                            None
+                        };
+                        self.heap_history(addr)?;
+                        return Ok(false);
+                    }
+                    _ => {}
+                }
                 let heap = self.debugger.get_heap_blocks();
                 for (addr, hb) in heap {
                     let waw = self.debugger.where_are_we(hb.alloc_addr)?;
                     if let Some(cf) = waw.cfile {
                         let file_name = self.debugger.lookup_file_by_id(cf).unwrap();
                         println!(
-                            "0x{:04x} size {} allocated at 0x{:04x} = {}:{}",
-                            addr, hb.size, hb.alloc_addr, file_name.short_name, waw.cline
+                            "0x{:04x} size {} #{} allocated at 0x{:04x} = {}:{}",
+                            addr,
+                            hb.size,
+                            hb.alloc_num,
+                            hb.alloc_addr,
+                            file_name.short_name,
+                            waw.cline
                         );
                     } else {
                         println!(
-                            "0x{:04x} size {} allocated at 0x{:04x} ",
-                            addr, hb.size, hb.alloc_addr
+                            "0x{:04x} size {} #{} allocated at 0x{:04x} ",
+                            addr, hb.size, hb.alloc_num, hb.alloc_addr
                         );
                     }
                 }
@@ -561,14 +698,30 @@ impl Shell {
                 println!("  source_mode: {:?}", self.source_mode);
                 println!("  source_tree: {}", self.debugger.get_cc65_dir().display());
                 println!("  dbg suffix: {}", self.debugger.dbg_suffix);
-                println!(
-                    "  traps: {}",
-                    if self.debugger.enable_heap_check {
-                        "On"
-                    } else {
-                        "Off"
-                    }
-                );
+                let on_off = |b: bool| if b { "On" } else { "Off" };
+                println!("  traps:");
+                println!("    stack: {}", on_off(self.debugger.enable_stack_check));
+                println!("    uninit: {}", on_off(self.debugger.enable_mem_check));
+                println!("    seg: {}", on_off(self.debugger.enable_seg_check));
+                println!("    heap: {}", on_off(self.debugger.enable_heap_check));
+                println!("    mode: {:?}", self.debugger.trap_mode);
+                println!("  uninit track: {}", on_off(self.debugger.uninit_track));
+                println!("  null guard: {}", self.debugger.null_guard);
+                println!("  lints: {}", self.debugger.lints);
+                if self.debugger.smc_allow.is_empty() {
+                    println!("  smc allowed: none");
+                } else {
+                    println!("  smc allowed: {}", self.debugger.smc_allow.join(","));
+                }
+                println!("  heap redzone: {}", self.debugger.heap_redzone);
+                println!("  heap quarantine: {}", self.debugger.quarantine_size);
+                println!("  heap verify: {}", self.debugger.heap_verify);
+                if let Some(num) = self.debugger.heap_break {
+                    println!("  heap break: #{}", num);
+                }
+                if let Some(policy) = self.debugger.heap_fail {
+                    println!("  heap fail: {}", policy);
+                }
                 println!(
                     "  verbose: {}",
                     VERBOSE.load(std::sync::atomic::Ordering::SeqCst)
@@ -599,6 +752,47 @@ impl Shell {
                     self.debugger.enable_heap_check(*t);
                     self.debugger.enable_stack_check(*t);
                     self.debugger.enable_mem_check(*t);
+                    self.debugger.enable_seg_check(*t);
+                }
+                if let Some(t) = args.get_one("stack_trap") {
+                    self.debugger.enable_stack_check(*t);
+                }
+                if let Some(t) = args.get_one("uninit_trap") {
+                    self.debugger.enable_mem_check(*t);
+                }
+                if let Some(t) = args.get_one("seg_trap") {
+                    self.debugger.enable_seg_check(*t);
+                }
+                if let Some(t) = args.get_one("heap_trap") {
+                    self.debugger.enable_heap_check(*t);
+                }
+                if let Some(t) = args.get_one::<bool>("uninit_track") {
+                    self.debugger.set_uninit_track(*t);
+                }
+                if let Some(size) = args.get_one::<u16>("null_guard") {
+                    self.debugger.set_null_guard(*size);
+                }
+                if let Some(lints) = args.get_one::<String>("lint") {
+                    self.debugger.set_lints(lints)?;
+                }
+                if let Some(modules) = args.get_one::<String>("smc") {
+                    self.debugger.set_smc_allow(modules);
+                }
+                if let Some(size) = args.get_one::<u16>("redzone") {
+                    self.debugger.set_heap_redzone(*size);
+                }
+                if let Some(t) = args.get_one::<bool>("heap_verify") {
+                    self.debugger.set_heap_verify(*t);
+                }
+                if let Some(size) = args.get_one::<u16>("quarantine") {
+                    self.debugger.set_quarantine_size(*size);
+                }
+                if let Some(mode) = args.get_one::<String>("trap_mode") {
+                    match mode.as_str() {
+                        "stop" => self.debugger.set_trap_mode(TrapMode::Stop),
+                        "warn" => self.debugger.set_trap_mode(TrapMode::Warn),
+                        _ => unreachable!(),
+                    };
                 }
                 if let Some(t) = args.get_one::<bool>("verbose") {
                     VERBOSE.store(*t, std::sync::atomic::Ordering::SeqCst);
@@ -687,30 +881,64 @@ impl Shell {
             }
             StopReason::Exit(_) => {
                 println!("Exit");
-                let heap = self.debugger.get_heap_blocks();
-                for (addr, hb) in heap {
-                    println!(
-                        "Heap block 0x{:04x} size {} leaked at {:04x}",
-                        addr, hb.size, hb.alloc_addr
-                    );
+                self.leak_report()?;
+                let usage = self.debugger.get_stack_usage();
+                if let Some(size) = usage.c_size {
+                    println!("C stack high water: {} of {} bytes", usage.c_used, size);
+                }
+                println!("Hardware stack high water: {} of 256 bytes", usage.hw_used);
+                let bugs = self.debugger.get_bug_log();
+                if !bugs.is_empty() {
+                    println!("Bug summary: {} distinct", bugs.len());
+                    for rep in bugs {
+                        println!(
+                            "  {} x {} at {}",
+                            rep.count,
+                            rep.bug,
+                            self.debugger.location_string(rep.pc)?
+                        );
+                    }
+                }
+                for sup in self.debugger.get_suppressions() {
+                    if sup.hits > 0 {
+                        println!("Suppressed {} x by '{}'", sup.hits, sup.name);
+                    }
                 }
                 return Ok(());
             }
             StopReason::Count | StopReason::Next => {}
-            StopReason::Bug(bug) => match bug {
-                BugType::SpMismatch => {
-                    println!("Stack pointer mismatch");
-                }
-                BugType::Memcheck(addr) => {
-                    println!("Unitialized memory read -> ${:04x}", addr);
-                }
-                BugType::HeapCheck => {
-                    println!("Heap check failed");
-                }
-                BugType::SegCheck(addr) => {
-                    println!("Seg read/write violation -> ${:04x}", addr);
+            StopReason::Bug(bug) => {
+                println!("{}", bug);
+                match bug {
+                    BugType::Memcheck(addr)
+                    | BugType::SegCheck(addr)
+                    | BugType::HeapOverflow(addr)
+                    | BugType::UseAfterFree(addr)
+                    | BugType::UninitValue(_, addr)
+                    | BugType::DeadStack(addr)
+                    | BugType::ExecViolation(addr)
+                    | BugType::CodeWrite(addr) => {
+                        self.explain_address(addr)?;
+                    }
+                    BugType::NullDeref(_) => {
+                        if let Some(ptr) = self.debugger.get_bug_pointer() {
+                            self.explain_address(ptr)?;
+                        }
+                    }
+                    BugType::ReturnCorrupted(expected, _) => {
+                        println!(
+                            "  should have returned to {}",
+                            self.debugger.location_string(expected)?
+                        );
+                    }
+                    BugType::StackOverflow(_) => {
+                        if let Some((bottom, top)) = self.debugger.stack_region {
+                            println!("  the c stack is ${:04x}-${:04x}", bottom, top - 1);
+                        }
+                    }
+                    _ => {}
                 }
-            },
+            }
             StopReason::WatchPoint(addr) => {
                 let wp = self.debugger.get_watch(addr).unwrap();
                 let wnum = self
@@ -725,6 +953,12 @@ impl Shell {
             StopReason::Finish => {
                 println!("Finish");
             }
+            StopReason::Catch(call) => {
+                println!("Catch {}", call);
+            }
+            StopReason::HeapBreak(num) => {
+                println!("Heap break at allocation #{}", num);
+            }
             StopReason::Ctrlc => {
                 println!("Ctrl-c break");
             }
@@ -770,15 +1004,285 @@ impl Shell {
 
         Ok(())
     }
+    fn leak_report(&self) -> Result<()> {
+        let leaks = self.debugger.leak_report()?;
+        if leaks.is_empty() {
+            return Ok(());
+        }
+        let text = |kind| match kind {
+            LeakKind::Definite => "definitely lost",
+            LeakKind::Indirect => "indirectly lost",
+            LeakKind::Reachable => "still reachable",
+        };
+        println!("Leak summary:");
+        for kind in [LeakKind::Definite, LeakKind::Indirect, LeakKind::Reachable] {
+            let groups = leaks.iter().filter(|g| g.kind == kind);
+            let blocks = groups.clone().map(|g| g.blocks.len()).sum::<usize>();
+            let bytes = groups.map(|g| g.bytes).sum::<usize>();
+            println!("  {}: {} bytes in {} blocks", text(kind), bytes, blocks);
+        }
+        // every block still allocated, reachable ones too
+        for group in &leaks {
+            println!(
+                "  {} bytes in {} blocks {}, allocated at {}",
+                group.bytes,
+                group.blocks.len(),
+                text(group.kind),
+                self.debugger.location_string(group.alloc_addr)?
+            );
+            let nums = group
+                .alloc_nums
+                .iter()
+                .map(|n| format!("#{}", n))
+                .collect::<Vec<_>>();
+            println!("      allocation {}", nums.join(" "));
+            for addr in &group.alloc_stack {
+                println!(
+                    "      called from {}",
+                    self.debugger.location_string(*addr)?
+                );
+            }
+        }
+        Ok(())
+    }
+    // list the cc65 heap as it really is, free blocks included
+    fn check_heap(&self) -> Result<()> {
+        let walk = self.debugger.check_heap()?;
+        println!(
+            "heap 0x{:04x}-0x{:04x}, top 0x{:04x}",
+            walk.org, walk.end, walk.ptr
+        );
+        for (start, size) in &walk.added {
+            println!("added 0x{:04x}-0x{:04x}", start, start + size - 1);
+        }
+        for chunk in &walk.chunks {
+            let Some(user) = chunk.user else {
+                println!("  0x{:04x} size {:<5} free", chunk.start, chunk.size);
+                continue;
+            };
+            if let Some(hb) = self.debugger.get_heap_blocks().get(&user) {
+                println!(
+                    "  0x{:04x} size {:<5} used 0x{:04x} size {} allocated at {}",
+                    chunk.start,
+                    chunk.size,
+                    user,
+                    hb.size,
+                    self.debugger.location_string(hb.alloc_addr)?
+                );
+            } else {
+                println!(
+                    "  0x{:04x} size {:<5} quarantined 0x{:04x}",
+                    chunk.start, chunk.size, user
+                );
+            }
+        }
+        if walk.errors.is_empty() {
+            println!("heap OK");
+        } else {
+            for err in &walk.errors {
+                println!("error: {}", err);
+            }
+        }
+        Ok(())
+    }
+    fn heap_stats(&self) -> Result<()> {
+        let walk = self.debugger.check_heap()?;
+        let stats = self.debugger.get_heap_stats();
+        let live = self
+            .debugger
+            .get_heap_blocks()
+            .values()
+            .filter(|hb| hb.addr != 0);
+        let blocks = live.clone().count();
+        let bytes = live.map(|hb| hb.size as usize).sum::<usize>();
+        println!(
+            "heap 0x{:04x}-0x{:04x}, {} bytes",
+            walk.org,
+            walk.end,
+            walk.end.saturating_sub(walk.org)
+        );
+        for (start, size) in &walk.added {
+            println!(
+                "  added 0x{:04x}-0x{:04x}, {} bytes",
+                start,
+                start + size - 1,
+                size
+            );
+        }
+        println!(
+            "  in use      : {} bytes in {} blocks (peak {} bytes in {} blocks)",
+            bytes, blocks, stats.peak_bytes, stats.peak_blocks
+        );
+        println!(
+            "  high water  : {} bytes of heap used (now {})",
+            stats.peak_top.max(walk.ptr).saturating_sub(walk.org),
+            walk.ptr.saturating_sub(walk.org)
+        );
+        // free list blocks plus the untouched space above the top
+        let free = walk.chunks.iter().filter(|c| c.user.is_none());
+        let free_count = free.clone().count();
+        let free_list = free.clone().map(|c| c.size as usize).sum::<usize>();
+        let above = walk.end.saturating_sub(walk.ptr) as usize;
+        let largest = free.map(|c| c.size as usize).max().unwrap_or(0).max(above);
+        let total = free_list + above;
+        let frag = (largest * 100).checked_div(total).map_or(0, |p| 100 - p);
+        println!(
+            "  free        : {} bytes in {} free blocks + {} above the top",
+            free_list, free_count, above
+        );
+        println!("  largest free: {} bytes, fragmentation {}%", largest, frag);
+        println!(
+            "  calls       : {} malloc, {} free, {} realloc, {} failed ({} injected)",
+            stats.mallocs, stats.frees, stats.reallocs, stats.failed, stats.injected
+        );
+        if self.debugger.redzone_size() != 0 || self.debugger.quarantine_budget() != 0 {
+            println!(
+                "  (heap use includes {} byte red zones and the {} byte quarantine)",
+                self.debugger.redzone_size(),
+                self.debugger.quarantine_budget()
+            );
+        }
+        if !walk.errors.is_empty() {
+            println!("heap is corrupt, see 'heap check'");
+        }
+        Ok(())
+    }
+    fn heap_history(&self, addr: Option<u16>) -> Result<()> {
+        let events = self.debugger.get_heap_events();
+        if events.len() == HEAP_HISTORY {
+            println!("(only the last {} heap calls are kept)", HEAP_HISTORY);
+        }
+        for ev in events.iter().filter(|e| addr.is_none_or(|a| e.touches(a))) {
+            let what = match ev.op {
+                HeapOp::Malloc => format!("malloc  0x{:04x} size {}", ev.addr, ev.size),
+                HeapOp::Free => format!("free    0x{:04x} size {}", ev.addr, ev.size),
+                HeapOp::Realloc { from, old_size } => format!(
+                    "realloc 0x{:04x} size {} -> 0x{:04x} size {}",
+                    from, old_size, ev.addr, ev.size
+                ),
+            };
+            let what = format!("{} (#{})", what, ev.alloc_num);
+            println!(
+                "#{:<5} {} at {} (tick {})",
+                ev.seq,
+                what,
+                self.debugger.location_string(ev.pc)?,
+                ev.ticks
+            );
+        }
+        Ok(())
+    }
+    // explain a bad data address, what instruction touched it and what is there
+    fn explain_address(&mut self, addr: u16) -> Result<()> {
+        if let Some(pc) = self.debugger.get_bug_pc() {
+            let mem = self.debugger.get_chunk(pc, 3)?;
+            self.debugger.dis(&mem, pc);
+            println!(
+                "  by {:<15} at {}",
+                self.debugger.dis_line,
+                self.debugger.location_string(pc)?
+            );
+        }
+        let info = self.debugger.describe_address(addr)?;
+        let mut known = false;
+        if let Some(seg) = &info.segment {
+            known = true;
+            match &info.symbol {
+                Some((name, 0)) => println!("  ${:04x} is {} in segment {}", addr, name, seg),
+                Some((name, off)) => {
+                    println!("  ${:04x} is {}+{} in segment {}", addr, name, off, seg)
+                }
+                None => println!("  ${:04x} is in segment {}", addr, seg),
+            }
+        }
+        if let Some(hb) = &info.heap {
+            known = true;
+            let state = if hb.free_addr.is_some() { "freed " } else { "" };
+            let off = addr - hb.addr;
+            if off < hb.size {
+                println!(
+                    "  ${:04x} is {} bytes into {}heap block 0x{:04x} size {}",
+                    addr, off, state, hb.addr, hb.size
+                );
+            } else {
+                println!(
+                    "  ${:04x} is {} bytes past the end of {}heap block 0x{:04x} size {}",
+                    addr,
+                    off - hb.size,
+                    state,
+                    hb.addr,
+                    hb.size
+                );
+            }
+            println!(
+                "    allocated at {}",
+                self.debugger.location_string(hb.alloc_addr)?
+            );
+            for addr in &hb.alloc_stack {
+                println!(
+                    "      called from {}",
+                    self.debugger.location_string(*addr)?
+                );
+            }
+            if let Some(free_addr) = hb.free_addr {
+                println!("    freed at {}", self.debugger.location_string(free_addr)?);
+                for addr in &hb.free_stack {
+                    println!(
+                        "      called from {}",
+                        self.debugger.location_string(*addr)?
+                    );
+                }
+            }
+        }
+        if let Some(off) = info.c_stack {
+            known = true;
+            match &info.cvar {
+                Some((name, 0)) => {
+                    println!("  ${:04x} is on the c stack, sp+{} ({})", addr, off, name)
+                }
+                Some((name, voff)) => println!(
+                    "  ${:04x} is on the c stack, sp+{} ({}+{})",
+                    addr, off, name, voff
+                ),
+                None => println!("  ${:04x} is on the c stack, sp+{}", addr, off),
+            }
+        }
+        if info.hw_stack {
+            known = true;
+            println!("  ${:04x} is on the hardware stack", addr);
+        }
+        if let Some(name) = &info.creg {
+            known = true;
+            println!("  ${:04x} is c register variable {}", addr, name);
+        }
+        if info.dead_stack {
+            known = true;
+            println!(
+                "  ${:04x} is below the stack pointer, popped when its function returned",
+                addr
+            );
+        }
+        if !known {
+            println!("  ${:04x} is not in any segment, heap block or stack", addr);
+        }
+        Ok(())
+    }
     fn print_reg_dis(&mut self, inst_addr: u16) {
         let mem = self.debugger.get_chunk(inst_addr, 3).unwrap();
-        self.debugger.dis(&mem, inst_addr);
+        let len = self.debugger.dis(&mem, inst_addr);
+        // '*' marks code the program has patched
+        let mark = if self.debugger.is_patched(inst_addr, len) {
+            '*'
+        } else {
+            ' '
+        };
 
         // print pc, dissasembled instruction and registers
         let stat = Status::from_bits_truncate(self.debugger.read_sr());
         println!(
-        "{:04x}:       {:<15} ac=${:02x} xr=${:02x} yr=${:02x} sp=${:02x} sp65=${:04x} sr=${:02x} {:?}",
+        "{:04x}:     {} {:<15} ac=${:02x} xr=${:02x} yr=${:02x} sp=${:02x} sp65=${:04x} sr=${:02x} {:?}",
         self.debugger.read_pc(),
+        mark,
         self.debugger.dis_line,
         self.debugger.read_ac(),
         self.debugger.read_xr(),
@@ -107,6 +107,18 @@ pub fn syntax() -> Command {
                 .arg(Arg::new("address").required(true))
                 .help_template(APPLET_TEMPLATE),
         )
+        .subcommand(
+            Command::new("rbreak")
+                .about("Set break points on all functions matching a regex")
+                .visible_alias("rb")
+                .arg(Arg::new("regex").required(true))
+                .arg(arg!(-m --module <module> "only functions in this module"))
+                .help_template(APPLET_TEMPLATE)
+                .after_help(
+                    "The break points form a group named after the regex,
+ 'dbp -g <regex>' deletes the whole group",
+                ),
+        )
         .subcommand(
             Command::new("watch")
                 .about("Set watch points")
@@ -116,6 +128,18 @@ pub fn syntax() -> Command {
                 .arg(arg!(-w --write  "watch for write"))
                 .help_template(APPLET_TEMPLATE),
         )
+        .subcommand(
+            Command::new("catch")
+                .about("Set catch point on a system call")
+                .arg(
+                    Arg::new("call")
+                        .required(true)
+                        .value_parser(["open", "close", "read", "write", "exit"]),
+                )
+                .arg(arg!(-b --before  "only stop before the call"))
+                .arg(arg!(-a --after  "only stop after the call"))
+                .help_template(APPLET_TEMPLATE),
+        )
         .subcommand(
             Command::new("list_breakpoints")
                 .about("List break points")
@@ -128,10 +152,17 @@ pub fn syntax() -> Command {
                 .alias("lwp")
                 .help_template(APPLET_TEMPLATE),
         )
+        .subcommand(
+            Command::new("list_catchpoints")
+                .about("List catch points")
+                .alias("lcp")
+                .help_template(APPLET_TEMPLATE),
+        )
         .subcommand(
             Command::new("delete_breakpoint")
                 .visible_alias("dbp")
                 .arg(Arg::new("id").required(false))
+                .arg(arg!(-g --group <group> "delete all break points set by one rbreak"))
                 .about("Delete breakpoint")
                 .help_template(APPLET_TEMPLATE),
         )
@@ -142,6 +173,21 @@ pub fn syntax() -> Command {
                 .about("Delete watchpoint")
                 .help_template(APPLET_TEMPLATE),
         )
+        .subcommand(
+            Command::new("delete_catchpoint")
+                .visible_alias("dcp")
+                .arg(Arg::new("id").required(false))
+                .about("Delete catchpoint (number or call name)")
+                .help_template(APPLET_TEMPLATE),
+        )
+        .subcommand(
+            Command::new("suppress")
+                .about("Load a bug suppression file, or list the loaded suppressions")
+                .arg(Arg::new("file").required(false))
+                .arg(arg!(-c --clear "remove all suppressions"))
+                .after_help("see 'about suppress' for the file format")
+                .help_template(APPLET_TEMPLATE),
+        )
         .subcommand(
             Command::new("dis")
                 .about("Disassemble")
@@ -159,7 +205,73 @@ pub fn syntax() -> Command {
             Command::new("display_heap")
                 .visible_aliases(["heap"])
                 .about("Display heap")
-           
+                .subcommand(
+                    Command::new("check")
+                        .about("Walk the cc65 heap and check it is intact")
+                        .help_template(APPLET_TEMPLATE),
+                )
+                .subcommand(
+                    Command::new("break")
+                        .about("Stop when allocation number n is made (0 to clear)")
+                        .arg(
+                            Arg::new("number")
+                                .required(false)
+                                .value_parser(clap::value_parser!(usize)),
+                        )
+                        .help_template(APPLET_TEMPLATE),
+                )
+                .subcommand(
+                    Command::new("fail")
+                        .about("Make malloc and realloc return NULL, to test out of memory handling")
+                        .arg(
+                            Arg::new("nth")
+                                .long("nth")
+                                .value_name("N")
+                                .help("fail allocation number N")
+                                .value_parser(clap::value_parser!(usize)),
+                        )
+                        .arg(
+                            Arg::new("after_bytes")
+                                .long("after-bytes")
+                                .value_name("B")
+                                .help("fail once B bytes have been asked for")
+                                .value_parser(clap::value_parser!(usize)),
+                        )
+                        .arg(
+                            Arg::new("probability")
+                                .long("probability")
+                                .value_name("P")
+                                .help("fail at random, P is 0 to 1")
+                                .value_parser(clap::value_parser!(f64)),
+                        )
+                        .arg(
+                            Arg::new("seed")
+                                .long("seed")
+                                .value_name("S")
+                                .help("seed for --probability (default 1)")
+                                .requires("probability")
+                                .value_parser(clap::value_parser!(u64)),
+                        )
+                        .arg(arg!(--off "stop failing allocations"))
+                        .group(ArgGroup::new("policy").args([
+                            "nth",
+                            "after_bytes",
+                            "probability",
+                            "off",
+                        ]))
+                        .help_template(APPLET_TEMPLATE),
+                )
+                .subcommand(
+                    Command::new("stats")
+                        .about("Heap usage, high water mark and fragmentation")
+                        .help_template(APPLET_TEMPLATE),
+                )
+                .subcommand(
+                    Command::new("history")
+                        .about("Who allocated and freed an address (all heap calls if none given)")
+                        .arg(Arg::new("address").required(false))
+                        .help_template(APPLET_TEMPLATE),
+                )
                 .help_template(APPLET_TEMPLATE),
         )
         .subcommand(
@@ -254,9 +366,58 @@ Match is a substring, eg 'lsy main' will list all symbols containing 'main'",
                     .value_parser(clap::builder::StringValueParser::new()),
                 )
                 .arg(
-                    arg!(traps: -t --traps <switch> "Turn traps on or off ")
+                    arg!(traps: -t --traps <switch> "Turn all traps on or off ")
+                        .value_parser(clap::builder::BoolishValueParser::new()),
+                )
+                .arg(
+                    arg!(stack_trap: --stack_trap <switch> "Turn stack balance trap on or off ")
                         .value_parser(clap::builder::BoolishValueParser::new()),
                 )
+                .arg(
+                    arg!(uninit_trap: --uninit_trap <switch> "Turn uninitialized read trap on or off ")
+                        .value_parser(clap::builder::BoolishValueParser::new()),
+                )
+                .arg(
+                    arg!(uninit_track: --uninit_track <switch> "Only report uninitialized values when they are used")
+                        .value_parser(clap::builder::BoolishValueParser::new()),
+                )
+                .arg(
+                    arg!(null_guard: --null_guard <bytes> "Pointers below this address count as NULL, 0 = off")
+                        .value_parser(clap::value_parser!(u16)),
+                )
+                .arg(
+                    arg!(smc: --smc <modules> "Modules allowed to patch their own code, comma separated, '*' for all, 'none'")
+                        .value_parser(clap::builder::StringValueParser::new()),
+                )
+                .arg(
+                    arg!(lint: --lint <lints> "6502 pitfall warnings: jmpind,decimal,brk,pvgap,midinsn, 'all', 'none', +name, -name")
+                        .allow_hyphen_values(true)
+                        .value_parser(clap::builder::StringValueParser::new()),
+                )
+                .arg(
+                    arg!(seg_trap: --seg_trap <switch> "Turn segment permission trap on or off ")
+                        .value_parser(clap::builder::BoolishValueParser::new()),
+                )
+                .arg(
+                    arg!(heap_trap: --heap_trap <switch> "Turn heap misuse trap on or off ")
+                        .value_parser(clap::builder::BoolishValueParser::new()),
+                )
+                .arg(
+                    arg!(redzone: --redzone <bytes> "Size of the guard zone after each heap block")
+                        .value_parser(clap::value_parser!(u16).range(0..=64)),
+                )
+                .arg(
+                    arg!(quarantine: --quarantine <bytes> "How many bytes of freed heap blocks to hold back from reuse")
+                        .value_parser(clap::value_parser!(u16)),
+                )
+                .arg(
+                    arg!(heap_verify: --heap_verify <switch> "Check the whole heap after every heap call")
+                        .value_parser(clap::builder::BoolishValueParser::new()),
+                )
+                .arg(
+                    arg!(trap_mode: -w --trap_mode <mode> "Stop on a trap or warn and continue")
+                        .value_parser(clap::builder::PossibleValuesParser::new(["stop", "warn"])),
+                )
                 .arg(
                     arg!(verbose: -v --verbose <switch> "Turn verbose messages on or off ")
                         .value_parser(clap::builder::BoolishValueParser::new()),